@@ -5,18 +5,23 @@ pub const MAX_RGB: f32 = 255.;
 
 pub const LINE: &str = "#FFFFFF";
 pub const HOVER: &str = "#66CCCC";
+pub const CONSTRUCTION: &str = "#5C8FD6";
+pub const FULLY_CONSTRAINED: &str = "#9D8DF1";
+pub const OVER_CONSTRAINED: &str = "#E5484D";
+pub const GRID_MAJOR: &str = "#7B969566";
+pub const GRID_MINOR: &str = "#7B969528";
 // pub const PRESSED: &str = "#CCCC00";
 // pub const SQUOOSH_ORANGE: &str = "#FF6600";
 // pub const SUNRISE_ORANGE: &str = "#F78B17";
 pub const CREAMSICLE_ORANGE: &str = "#FA821E";
 // pub const AMBER_ORANGE: &str = "#E49B5D";
-// pub const COOL_BLUE: &str = "#7B9695";
+pub const COOL_BLUE: &str = "#7B9695";
 pub const LEAF_GREEN: &str = "#69BA5B";
 // pub const FOREST_GREEN: &str = "#32542C";
 pub const DARK_SEAFOAM: &str = "#2B5449";
 // pub const SAGE_GREEN: &str = "#78997A";
-// pub const DARK_SAGE: &str = "#253333";
-// pub const GOLD_YELLOW: &str = "#EBC06D";
+pub const DARK_SAGE: &str = "#253333";
+pub const GOLD_YELLOW: &str = "#EBC06D";
 
 pub fn color_from_hex(hex: &str) -> Color {
     if hex.len() < 7 || hex.len() == 8 || hex.len() > 9 || !hex.starts_with('#') {
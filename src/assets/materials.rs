@@ -1,21 +1,44 @@
 use bevy::prelude::*;
 
+use crate::constraints::constraint::Constraint;
+use crate::constraints::diagnostic::Conflicted;
+use crate::constraints::freedom::ConstraintState;
+use crate::constraints::simulation::Trace;
 use crate::cursor::Picking;
 use crate::schedule::ScheduleSet;
+use crate::sketching::arc::Arc;
+use crate::sketching::circle::Circle;
+use crate::sketching::construction::Construction;
 use crate::sketching::dot::Dot;
+use crate::sketching::ellipse::{Ellipse, EllipticalArc};
 use crate::sketching::line::Line;
 use crate::sketching::selection::Selected;
 use crate::sketching::sketch::is_dragging;
+use crate::sketching::spline::Spline;
 
 use super::colors::*;
 
 type ChangingButNotSelected<T> = (With<T>, With<ChangingMaterial>, Without<Selected>);
 type ChangingAndSelected = (With<ChangingMaterial>, With<Selected>);
+type MaterialWithState = (
+    Entity,
+    &'static mut MeshMaterial3d<StandardMaterial>,
+    Has<Construction>,
+    Option<&'static ConstraintState>,
+    Has<Conflicted>,
+);
 
 #[derive(Resource, Default)]
 pub struct UIMaterials {
     pub dot: Handle<StandardMaterial>,
     pub line: Handle<StandardMaterial>,
+    pub construction: Handle<StandardMaterial>,
+    // Under-constrained geometry keeps its own material, like geometry no constraint has
+    // reached yet, which is just as free
+    pub fully_constrained: Handle<StandardMaterial>,
+    pub over_constrained: Handle<StandardMaterial>,
+    // Plates behind constraint glyphs
+    pub glyph: Handle<StandardMaterial>,
     pub hover: Handle<StandardMaterial>,
     pub selected: Handle<StandardMaterial>,
     pub focused: Handle<StandardMaterial>,
@@ -47,8 +70,22 @@ impl Plugin for MaterialsPlugin {
                     update_to_selected_material,
                     update_to_hover_material::<Dot>,
                     update_to_hover_material::<Line>,
+                    update_to_hover_material::<Circle>,
+                    update_to_hover_material::<Arc>,
+                    update_to_hover_material::<Spline>,
+                    update_to_hover_material::<Ellipse>,
+                    update_to_hover_material::<EllipticalArc>,
+                    update_to_hover_material::<Constraint>,
+                    update_to_hover_material::<Trace>,
                     update_to_default_material::<Dot>,
                     update_to_default_material::<Line>,
+                    update_to_default_material::<Circle>,
+                    update_to_default_material::<Arc>,
+                    update_to_default_material::<Spline>,
+                    update_to_default_material::<Ellipse>,
+                    update_to_default_material::<EllipticalArc>,
+                    update_to_default_material::<Constraint>,
+                    update_to_default_material::<Trace>,
                 )
                     .run_if(not(is_dragging()))
                     .chain()
@@ -61,6 +98,10 @@ pub fn setup_ui_materials(mut commands: Commands, mut materials: ResMut<Assets<S
     commands.insert_resource(UIMaterials {
         dot: materials.add(ui_material(color_from_hex(CREAMSICLE_ORANGE))),
         line: materials.add(ui_material(color_from_hex(LINE))),
+        construction: materials.add(ui_material(color_from_hex(CONSTRUCTION))),
+        fully_constrained: materials.add(ui_material(color_from_hex(FULLY_CONSTRAINED))),
+        over_constrained: materials.add(ui_material(color_from_hex(OVER_CONSTRAINED))),
+        glyph: materials.add(ui_material(color_from_hex(DARK_SAGE))),
         hover: materials.add(ui_material(color_from_hex(HOVER))),
         selected: materials.add(ui_material(color_from_hex(LEAF_GREEN))),
         focused: materials.add(ui_material(color_from_hex(DARK_SEAFOAM))),
@@ -78,13 +119,16 @@ pub fn ui_material(color: Color) -> StandardMaterial {
 pub fn update_to_default_material<T: Component + UIMaterialProvider>(
     mut commands: Commands,
     ui_materials: Res<UIMaterials>,
-    mut material_query: Query<
-        (Entity, &mut MeshMaterial3d<StandardMaterial>),
-        ChangingButNotSelected<T>,
-    >,
+    mut material_query: Query<MaterialWithState, ChangingButNotSelected<T>>,
 ) {
-    for (entity, mut material) in material_query.iter_mut() {
-        let mat = get_ui_material::<T>(&ui_materials);
+    for (entity, mut material, is_construction, state, is_conflicted) in material_query.iter_mut() {
+        let mat = match (state, is_construction) {
+            _ if is_conflicted => ui_materials.over_constrained.clone(),
+            (Some(ConstraintState::Over), _) => ui_materials.over_constrained.clone(),
+            (_, true) => ui_materials.construction.clone(),
+            (Some(ConstraintState::Full), _) => ui_materials.fully_constrained.clone(),
+            (Some(ConstraintState::Under) | None, _) => get_ui_material::<T>(&ui_materials),
+        };
         // println!("Changing {:?} to default material", entity);
         material.0 = mat;
         commands.entity(entity).remove::<ChangingMaterial>();
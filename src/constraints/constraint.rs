@@ -0,0 +1,246 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::{input_just_pressed, input_just_released};
+use bevy::prelude::*;
+
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+use crate::sketching::arc::Arc;
+use crate::sketching::circle::Circle;
+use crate::sketching::curve::delete_dependent_curves;
+use crate::sketching::dot::Dot;
+use crate::sketching::line::{Line, delete_dependent_lines, update_line_mesh_transforms};
+use crate::sketching::selection::Selected;
+use crate::sketching::sketch::{SketchMode, is_dragging, update_moving_transforms};
+
+use super::diagnostic::{
+    ConstraintDiagnostic, Pending, clear_conflicts, handle_constraint_diagnostics,
+};
+use super::dimension::DimensionPlugin;
+use super::freedom::{SketchFreedom, analyze_freedom};
+use super::glyph::GlyphPlugin;
+use super::inference::InferencePlugin;
+use super::parameter::ParameterPlugin;
+use super::simulation::SimulationPlugin;
+use super::solver::{ConstraintSolver, mark_solver_dirty, needs_solve, solve_constraints};
+
+type SelectedDots<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform), (With<Dot>, With<Selected>)>;
+type SelectedLines<'w, 's> = Query<'w, 's, Entity, (With<Line>, With<Selected>)>;
+type SelectedCurves<'w, 's> =
+    Query<'w, 's, Entity, (Or<(With<Line>, With<Circle>, With<Arc>)>, With<Selected>)>;
+type SketchedEntities<'w, 's> =
+    Query<'w, 's, (), Or<(With<Dot>, With<Line>, With<Circle>, With<Arc>)>>;
+
+// Design intent between dots and lines, kept true by the solver
+#[derive(Component, Debug, Clone, PartialEq)]
+pub enum Constraint {
+    // Two dots
+    Coincident(Entity, Entity),
+    // One line
+    Horizontal(Entity),
+    Vertical(Entity),
+    // Two lines
+    Parallel(Entity, Entity),
+    Perpendicular(Entity, Entity),
+    EqualLength(Entity, Entity),
+    // One dot held at a position
+    Fixed(Entity, Vec3),
+    // A dot and the line, circle or arc it lies on
+    PointOnCurve(Entity, Entity),
+    // A dot and the line it halves
+    Midpoint(Entity, Entity),
+}
+
+impl Constraint {
+    // Dots or lines the constraint refers to
+    pub fn entities(&self) -> Vec<Entity> {
+        match *self {
+            Constraint::Coincident(a, b)
+            | Constraint::Parallel(a, b)
+            | Constraint::Perpendicular(a, b)
+            | Constraint::EqualLength(a, b)
+            | Constraint::PointOnCurve(a, b)
+            | Constraint::Midpoint(a, b) => vec![a, b],
+            Constraint::Horizontal(entity)
+            | Constraint::Vertical(entity)
+            | Constraint::Fixed(entity, _) => vec![entity],
+        }
+    }
+
+    // Short symbol shown next to the constrained geometry
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Constraint::Coincident(..) => "o",
+            Constraint::Horizontal(_) => "H",
+            Constraint::Vertical(_) => "V",
+            Constraint::Parallel(..) => "//",
+            Constraint::Perpendicular(..) => "_|_",
+            Constraint::EqualLength(..) => "=",
+            Constraint::Fixed(..) => "#",
+            Constraint::PointOnCurve(..) => "-o-",
+            Constraint::Midpoint(..) => "M",
+        }
+    }
+}
+
+pub struct ConstraintPlugin;
+
+impl Plugin for ConstraintPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ConstraintSolver::default())
+            .add_event::<ConstraintDiagnostic>()
+            .insert_resource(SketchFreedom::default())
+            .add_plugins(DimensionPlugin)
+            .add_plugins(InferencePlugin)
+            .add_plugins(GlyphPlugin)
+            .add_plugins(ParameterPlugin)
+            .add_plugins(SimulationPlugin)
+            .add_systems(
+                Update,
+                (
+                    handle_constraint_shortcuts.run_if(
+                        in_state(SketchMode::None)
+                            .and(is_alt_pressed)
+                            .and(not(is_shift_pressed)),
+                    ),
+                    mark_solver_dirty
+                        .run_if(input_just_released(MouseButton::Left).or(is_dragging())),
+                    clear_conflicts.run_if(needs_solve),
+                    solve_constraints.run_if(needs_solve),
+                    handle_constraint_diagnostics.run_if(any_with_component::<Pending>),
+                    analyze_freedom
+                        .run_if(resource_changed::<ConstraintSolver>.and(not(is_dragging()))),
+                )
+                    .chain()
+                    .after(update_moving_transforms)
+                    .before(update_line_mesh_transforms)
+                    .in_set(ScheduleSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                // Deleted geometry can free up the rest of the sketch
+                (delete_dependent_constraints, mark_solver_dirty)
+                    .chain()
+                    .after(delete_dependent_lines)
+                    .after(delete_dependent_curves::<Circle>)
+                    .after(delete_dependent_curves::<Arc>)
+                    .run_if(input_just_pressed(KeyCode::KeyX))
+                    .in_set(ScheduleSet::DespawnEntities),
+            );
+    }
+}
+
+pub fn is_alt_pressed(keyboard: Res<ButtonInput<KeyCode>>) -> bool {
+    keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+}
+
+// Ctrl sketches without any automatic constraints
+pub fn is_ctrl_pressed(keyboard: Res<ButtonInput<KeyCode>>) -> bool {
+    keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
+
+// Alt+Shift combinations are dimension shortcuts
+pub fn is_shift_pressed(keyboard: Res<ButtonInput<KeyCode>>) -> bool {
+    keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+// Alt plus a key constrains the selection:
+// C coincident, H horizontal, V vertical, P parallel, L perpendicular, E equal length, F fixed,
+// O dot on a line, circle or arc, M dot at the middle of a line
+pub fn handle_constraint_shortcuts(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut solver: ResMut<ConstraintSolver>,
+    dots: SelectedDots,
+    lines: SelectedLines,
+    curves: SelectedCurves,
+) {
+    let selected_dots: Vec<Entity> = dots.iter().map(|(dot, _)| dot).collect();
+    let selected_lines: Vec<Entity> = lines.iter().collect();
+
+    let constraints = if keyboard.just_pressed(KeyCode::KeyC) {
+        match selected_dots[..] {
+            [a, b] => vec![Constraint::Coincident(a, b)],
+            _ => Vec::new(),
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyH) {
+        selected_lines
+            .iter()
+            .map(|line| Constraint::Horizontal(*line))
+            .collect()
+    } else if keyboard.just_pressed(KeyCode::KeyV) {
+        selected_lines
+            .iter()
+            .map(|line| Constraint::Vertical(*line))
+            .collect()
+    } else if keyboard.just_pressed(KeyCode::KeyP) {
+        line_pair(&selected_lines, Constraint::Parallel)
+    } else if keyboard.just_pressed(KeyCode::KeyL) {
+        line_pair(&selected_lines, Constraint::Perpendicular)
+    } else if keyboard.just_pressed(KeyCode::KeyE) {
+        line_pair(&selected_lines, Constraint::EqualLength)
+    } else if keyboard.just_pressed(KeyCode::KeyF) {
+        dots.iter()
+            .map(|(dot, transform)| Constraint::Fixed(dot, transform.translation))
+            .collect()
+    } else if keyboard.just_pressed(KeyCode::KeyO) {
+        let selected_curves: Vec<Entity> = curves.iter().collect();
+        match (&selected_dots[..], &selected_curves[..]) {
+            ([dot], [curve]) => vec![Constraint::PointOnCurve(*dot, *curve)],
+            _ => Vec::new(),
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyM) {
+        match (&selected_dots[..], &selected_lines[..]) {
+            ([dot], [line]) => vec![Constraint::Midpoint(*dot, *line)],
+            _ => Vec::new(),
+        }
+    } else {
+        return;
+    };
+
+    if constraints.is_empty() {
+        warn!("Selection does not fit the constraint!");
+        return;
+    }
+    for constraint in constraints {
+        spawn_constraint(&mut commands, constraint);
+    }
+    solver.is_dirty = true;
+}
+
+// Constraints between lines need exactly two of them selected
+fn line_pair(lines: &[Entity], constraint: fn(Entity, Entity) -> Constraint) -> Vec<Constraint> {
+    match lines[..] {
+        [first, second] => vec![constraint(first, second)],
+        _ => Vec::new(),
+    }
+}
+
+pub fn spawn_constraint(commands: &mut Commands, constraint: Constraint) -> Entity {
+    commands
+        .spawn((
+            constraint,
+            Pending::Added,
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id()
+}
+
+// Delete constraints if any dot, line or curve they refer to has been deleted
+pub fn delete_dependent_constraints(
+    mut commands: Commands,
+    constraints: Query<(Entity, &Constraint)>,
+    sketched: SketchedEntities,
+) {
+    for (entity, constraint) in constraints.iter() {
+        let is_dangling = constraint
+            .entities()
+            .iter()
+            .any(|referenced| !sketched.contains(*referenced));
+        if is_dangling {
+            commands.entity(entity).despawn();
+        }
+    }
+}
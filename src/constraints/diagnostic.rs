@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+
+use crate::assets::materials::ChangingMaterial;
+
+use super::constraint::Constraint;
+use super::dimension::{Dimension, evaluate_dimension};
+use super::parameter::Parameters;
+
+// Constraint or dimension the solver has not accepted yet
+#[derive(Component, Debug, Clone, PartialEq)]
+#[component(storage = "SparseSet")]
+pub enum Pending {
+    Added,
+    // Dimension whose value and expression were changed from the ones kept here
+    Edited(f32, Option<String>),
+}
+
+// Geometry taking part in the latest conflict
+#[derive(Component, Default)]
+#[component(storage = "SparseSet")]
+pub struct Conflicted;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    // Implied by other constraints, so it is kept but adds nothing
+    Redundant,
+    // Cannot hold together with other constraints, so the pending ones are rejected
+    Conflicting,
+}
+
+// Smallest set of constraints and dimensions that depend on each other
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ConstraintDiagnostic {
+    pub kind: DiagnosticKind,
+    pub constraints: Vec<Entity>,
+    pub geometry: Vec<Entity>,
+    // Pending constraints removed and dimension edits reverted because of it
+    pub rejected: Vec<Entity>,
+}
+
+// Report diagnostics, highlight their geometry and settle pending constraints
+pub fn handle_constraint_diagnostics(
+    mut commands: Commands,
+    mut diagnostics: EventReader<ConstraintDiagnostic>,
+    pending: Query<(Entity, &Pending)>,
+    constraints: Query<&Constraint>,
+    mut dimensions: Query<&mut Dimension>,
+    parameters: Res<Parameters>,
+) {
+    let mut rejected = Vec::new();
+    for diagnostic in diagnostics.read() {
+        let described: Vec<String> = diagnostic
+            .constraints
+            .iter()
+            .map(|entity| {
+                if let Ok(constraint) = constraints.get(*entity) {
+                    format!("{:?}", constraint)
+                } else if let Ok(dimension) = dimensions.get(*entity) {
+                    format!(
+                        "{:?} = {}",
+                        dimension.kind,
+                        dimension.kind.format(dimension.value)
+                    )
+                } else {
+                    format!("{:?}", entity)
+                }
+            })
+            .collect();
+        warn!(
+            "{:?} constraints: {}{}",
+            diagnostic.kind,
+            described.join(", "),
+            if diagnostic.rejected.is_empty() {
+                String::new()
+            } else {
+                format!("; undone: {:?}", diagnostic.rejected)
+            }
+        );
+        for entity in &diagnostic.geometry {
+            commands
+                .entity(*entity)
+                .try_insert((Conflicted, ChangingMaterial));
+        }
+        rejected.extend(diagnostic.rejected.iter().copied());
+    }
+
+    for (entity, pending) in pending.iter() {
+        if rejected.contains(&entity) {
+            match pending {
+                Pending::Added => {
+                    commands.entity(entity).despawn();
+                    continue;
+                }
+                Pending::Edited(value, expression) => {
+                    if let Ok(mut dimension) = dimensions.get_mut(entity) {
+                        dimension.value = *value;
+                        dimension.expression = expression.clone();
+                        warn_if_behind_parameters(&parameters, &dimension);
+                    }
+                }
+            }
+        }
+        commands.entity(entity).remove::<Pending>();
+    }
+}
+
+fn warn_if_behind_parameters(parameters: &Parameters, dimension: &Dimension) {
+    let Some(expression) = &dimension.expression else {
+        return;
+    };
+    if let Ok((value, _)) = evaluate_dimension(parameters, dimension.kind, expression)
+        && (dimension.value - value).abs() > f32::EPSILON
+    {
+        warn!(
+            "Dimension {:?} no longer matches its parameters, kept at {}",
+            expression,
+            dimension.kind.format(dimension.value)
+        );
+    }
+}
+
+pub fn clear_conflicts(mut commands: Commands, conflicted: Query<Entity, With<Conflicted>>) {
+    for entity in conflicted.iter() {
+        commands
+            .entity(entity)
+            .remove::<Conflicted>()
+            .insert(ChangingMaterial);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec2;
+
+    use super::super::solver::{ConstrainedSketch, Equation};
+    use super::*;
+
+    fn sketch(points: &[(f64, f64)], equations: &[(Equation, u32)]) -> ConstrainedSketch {
+        let mut sketch = ConstrainedSketch::default();
+        for (x, y) in points {
+            sketch.system.add_point(DVec2::new(*x, *y));
+        }
+        for (equation, source) in equations {
+            sketch.system.equations.push(*equation);
+            sketch.sources.push(Entity::from_raw(*source));
+        }
+        sketch
+    }
+
+    fn sources(sketch: &ConstrainedSketch) -> Vec<Vec<u32>> {
+        sketch
+            .system
+            .analyze()
+            .circuits
+            .iter()
+            .map(|circuit| {
+                let mut sources: Vec<u32> = sketch
+                    .circuit_sources(circuit)
+                    .iter()
+                    .map(|entity| entity.index())
+                    .collect();
+                sources.sort();
+                sources
+            })
+            .collect()
+    }
+
+    #[test]
+    fn conflicting_set_leaves_out_unrelated_constraints() {
+        // Horizontal and vertical pin the end onto the fixed start, so the distance can not hold
+        let mut sketch = sketch(
+            &[(0., 0.), (1., 0.), (5., 5.), (6., 5.)],
+            &[
+                (Equation::Horizontal(0, 1), 1),
+                (Equation::Distance(2, 3, 1.), 2),
+                (Equation::Distance(0, 1, 1.), 3),
+                (Equation::Vertical(0, 1), 4),
+            ],
+        );
+        sketch.system.locked[0] = true;
+        sketch.fixed.push((0, Entity::from_raw(5)));
+        assert_eq!(sources(&sketch), vec![vec![3, 4, 5]]);
+    }
+
+    #[test]
+    fn repeated_constraint_is_its_own_redundant_set() {
+        let sketch = sketch(
+            &[(0., 0.), (1., 0.), (1., 1.)],
+            &[
+                (Equation::Horizontal(0, 1), 1),
+                (Equation::Vertical(1, 2), 2),
+                (Equation::Horizontal(0, 1), 3),
+            ],
+        );
+        let analysis = sketch.system.analyze();
+        assert_eq!(analysis.redundant, vec![2]);
+        assert_eq!(sources(&sketch), vec![vec![1, 3]]);
+    }
+
+    #[test]
+    fn independent_constraints_have_no_dependent_set() {
+        let sketch = sketch(
+            &[(0., 0.), (1., 0.), (1., 1.)],
+            &[
+                (Equation::Horizontal(0, 1), 1),
+                (Equation::Vertical(1, 2), 2),
+                (Equation::Distance(0, 2, 2.), 3),
+            ],
+        );
+        assert!(sources(&sketch).is_empty());
+    }
+}
@@ -0,0 +1,676 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::ecs::system::SystemParam;
+use bevy::input::InputSystem;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::cursor::Picking;
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+use crate::sketching::arc::{Arc, arc_center};
+use crate::sketching::circle::Circle;
+use crate::sketching::curve::{DotTransforms, delete_dependent_curves};
+use crate::sketching::dot::Dot;
+use crate::sketching::line::{Line, delete_dependent_lines};
+use crate::sketching::selection::Selected;
+use crate::sketching::sketch::SketchMode;
+
+use super::constraint::is_alt_pressed;
+use super::diagnostic::Pending;
+use super::expression::{Expression, ExpressionError, Unit};
+use super::parameter::{Parameters, type_text};
+use super::solver::{ConstraintSolver, needs_solve, solve_constraints};
+
+// Distance of dimension lines from what they measure
+const DIMENSION_OFFSET: f32 = 0.3;
+const DIMENSION_FONT_SIZE: f32 = 14.;
+const MAX_ANGLE: f32 = 180.;
+
+type SelectedEntities<'w, 's> =
+    Query<'w, 's, (Entity, Has<Dot>, Has<Line>), (With<Selected>, Without<Dimension>)>;
+type SketchedEntities<'w, 's> =
+    Query<'w, 's, (), Or<(With<Dot>, With<Line>, With<Circle>, With<Arc>)>>;
+type DimensionLabels<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static DimensionLabel,
+        &'static mut Text,
+        &'static mut Node,
+        &'static mut TextColor,
+    ),
+>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DimensionKind {
+    // Two dots
+    Distance(Entity, Entity),
+    HorizontalDistance(Entity, Entity),
+    VerticalDistance(Entity, Entity),
+    // One line
+    LineLength(Entity),
+    // Two lines, in degrees
+    Angle(Entity, Entity),
+    // One circle or arc
+    Radius(Entity),
+    Diameter(Entity),
+}
+
+// Driving measurement whose value the solver holds the geometry to
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Dimension {
+    pub kind: DimensionKind,
+    pub value: f32,
+    // Parameter expression the value follows, if any
+    pub expression: Option<String>,
+    // Angles swept by a simulation are directed, so they can turn all the way round
+    pub is_signed: bool,
+}
+
+// Screen space text showing the value of a dimension
+#[derive(Component, Debug)]
+pub struct DimensionLabel {
+    pub dimension: Entity,
+}
+
+// Value being typed for a dimension, committed with Enter
+#[derive(Resource, Debug, Default)]
+pub struct DimensionInput {
+    pub dimension: Option<Entity>,
+    pub text: String,
+}
+
+// Dimension lines and where the value is shown
+pub struct Annotation {
+    pub strips: Vec<Vec<Vec3>>,
+    pub label: Vec3,
+}
+
+impl DimensionKind {
+    // Dots, lines or curves the dimension refers to
+    pub fn entities(&self) -> Vec<Entity> {
+        match *self {
+            DimensionKind::Distance(a, b)
+            | DimensionKind::HorizontalDistance(a, b)
+            | DimensionKind::VerticalDistance(a, b)
+            | DimensionKind::Angle(a, b) => vec![a, b],
+            DimensionKind::LineLength(entity)
+            | DimensionKind::Radius(entity)
+            | DimensionKind::Diameter(entity) => vec![entity],
+        }
+    }
+
+    pub fn format(&self, value: f32) -> String {
+        match self {
+            DimensionKind::Angle(..) => format!("{:.1}°", value),
+            DimensionKind::Radius(_) => format!("R{:.2}", value),
+            DimensionKind::Diameter(_) => format!("Ø{:.2}", value),
+            _ => format!("{:.2}", value),
+        }
+    }
+
+    fn unit(&self) -> Unit {
+        match self {
+            DimensionKind::Angle(..) => Unit::Angle,
+            _ => Unit::Length,
+        }
+    }
+
+    pub fn is_valid(&self, value: f32) -> bool {
+        match self {
+            DimensionKind::Angle(..) => (0.0..=MAX_ANGLE).contains(&value),
+            _ => value > 0.,
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct DimensionGeometry<'w, 's> {
+    lines: Query<'w, 's, &'static Line>,
+    circles: Query<'w, 's, &'static Circle>,
+    arcs: Query<'w, 's, &'static Arc>,
+    dots: DotTransforms<'w, 's>,
+}
+
+impl DimensionGeometry<'_, '_> {
+    fn position(&self, dot: Entity) -> Option<Vec3> {
+        self.dots
+            .get(dot)
+            .ok()
+            .map(|transform| transform.translation)
+    }
+
+    pub fn line_dots(&self, line: Entity) -> Option<(Entity, Entity)> {
+        let line = self.lines.get(line).ok()?;
+        Some((line.start, line.end))
+    }
+
+    fn line_ends(&self, line: Entity) -> Option<(Vec3, Vec3)> {
+        let (start, end) = self.line_dots(line)?;
+        Some((self.position(start)?, self.position(end)?))
+    }
+
+    // Center and radius of a circle or arc
+    pub fn circle(&self, curve: Entity) -> Option<(Vec3, f32)> {
+        if let Ok(circle) = self.circles.get(curve) {
+            return Some((self.position(circle.center)?, circle.radius));
+        }
+        let arc = self.arcs.get(curve).ok()?;
+        arc_center(
+            self.position(arc.start)?,
+            self.position(arc.end)?,
+            arc.sweep,
+        )
+    }
+
+    pub fn measure(&self, kind: DimensionKind) -> Option<f32> {
+        match kind {
+            DimensionKind::Distance(a, b) => Some(self.position(a)?.distance(self.position(b)?)),
+            DimensionKind::HorizontalDistance(a, b) => {
+                Some((self.position(b)?.x - self.position(a)?.x).abs())
+            }
+            DimensionKind::VerticalDistance(a, b) => {
+                Some((self.position(b)?.y - self.position(a)?.y).abs())
+            }
+            DimensionKind::LineLength(line) => {
+                let (start, end) = self.line_ends(line)?;
+                Some(start.distance(end))
+            }
+            DimensionKind::Angle(first, second) => {
+                let (first_start, first_end) = self.line_ends(first)?;
+                let (second_start, second_end) = self.line_ends(second)?;
+                let first = (first_end - first_start).truncate();
+                let second = (second_end - second_start).truncate();
+                Some(first.angle_to(second).abs().to_degrees())
+            }
+            DimensionKind::Radius(curve) => Some(self.circle(curve)?.1),
+            DimensionKind::Diameter(curve) => Some(self.circle(curve)?.1 * 2.),
+        }
+    }
+
+    pub fn annotation(&self, kind: DimensionKind) -> Option<Annotation> {
+        match kind {
+            DimensionKind::Distance(a, b) => {
+                Some(aligned_annotation(self.position(a)?, self.position(b)?))
+            }
+            DimensionKind::LineLength(line) => {
+                let (start, end) = self.line_ends(line)?;
+                Some(aligned_annotation(start, end))
+            }
+            DimensionKind::HorizontalDistance(a, b) => {
+                let (a, b) = (self.position(a)?, self.position(b)?);
+                let y = a.y.max(b.y) + DIMENSION_OFFSET;
+                let (a_end, b_end) = (Vec3::new(a.x, y, a.z), Vec3::new(b.x, y, b.z));
+                Some(Annotation {
+                    strips: vec![vec![a, a_end], vec![b, b_end], vec![a_end, b_end]],
+                    label: (a_end + b_end) / 2. + Vec3::Y * DIMENSION_OFFSET / 2.,
+                })
+            }
+            DimensionKind::VerticalDistance(a, b) => {
+                let (a, b) = (self.position(a)?, self.position(b)?);
+                let x = a.x.max(b.x) + DIMENSION_OFFSET;
+                let (a_end, b_end) = (Vec3::new(x, a.y, a.z), Vec3::new(x, b.y, b.z));
+                Some(Annotation {
+                    strips: vec![vec![a, a_end], vec![b, b_end], vec![a_end, b_end]],
+                    label: (a_end + b_end) / 2. + Vec3::X * DIMENSION_OFFSET / 2.,
+                })
+            }
+            DimensionKind::Angle(first, second) => {
+                let first = self.line_ends(first)?;
+                let second = self.line_ends(second)?;
+                Some(angle_annotation(first, second))
+            }
+            DimensionKind::Radius(curve) => {
+                let (center, radius) = self.circle(curve)?;
+                let direction = Vec3::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.);
+                let edge = center + direction * radius;
+                Some(Annotation {
+                    strips: vec![vec![center, edge]],
+                    label: edge + direction * DIMENSION_OFFSET / 2.,
+                })
+            }
+            DimensionKind::Diameter(curve) => {
+                let (center, radius) = self.circle(curve)?;
+                let direction = Vec3::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.);
+                let edge = center + direction * radius;
+                Some(Annotation {
+                    strips: vec![vec![center - direction * radius, edge]],
+                    label: edge + direction * DIMENSION_OFFSET / 2.,
+                })
+            }
+        }
+    }
+}
+
+// Dimension line parallel to the measured segment, on its left side
+fn aligned_annotation(start: Vec3, end: Vec3) -> Annotation {
+    let direction = (end - start).normalize_or_zero();
+    let offset = Vec3::new(-direction.y, direction.x, 0.) * DIMENSION_OFFSET;
+    Annotation {
+        strips: vec![
+            vec![start, start + offset],
+            vec![end, end + offset],
+            vec![start + offset, end + offset],
+        ],
+        label: (start + end) / 2. + offset * 1.5,
+    }
+}
+
+// Arc between the directions of two lines around where they meet
+fn angle_annotation(first: (Vec3, Vec3), second: (Vec3, Vec3)) -> Annotation {
+    let first_direction = (first.1 - first.0).normalize_or_zero();
+    let second_direction = (second.1 - second.0).normalize_or_zero();
+    let cross = first_direction
+        .truncate()
+        .perp_dot(second_direction.truncate());
+    let vertex = if cross.abs() < f32::EPSILON {
+        (first.0 + first.1 + second.0 + second.1) / 4.
+    } else {
+        let t = (second.0 - first.0)
+            .truncate()
+            .perp_dot(second_direction.truncate())
+            / cross;
+        first.0 + first_direction * t
+    };
+    let sweep = first_direction
+        .truncate()
+        .angle_to(second_direction.truncate());
+    let radius = DIMENSION_OFFSET * 1.5;
+    let arc = (0..=16)
+        .map(|i| vertex + Quat::from_rotation_z(sweep * i as f32 / 16.) * first_direction * radius)
+        .collect();
+    let bisector = Quat::from_rotation_z(sweep / 2.) * first_direction;
+    Annotation {
+        strips: vec![arc],
+        label: vertex + bisector * (radius + DIMENSION_OFFSET / 2.),
+    }
+}
+
+pub struct DimensionPlugin;
+
+impl Plugin for DimensionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DimensionInput::default())
+            .add_systems(
+                Update,
+                (
+                    handle_dimension_shortcuts
+                        .run_if(in_state(SketchMode::None).and(is_alt_pressed)),
+                    drive_parameter_dimensions.run_if(resource_changed::<Parameters>),
+                    drive_circle_dimensions.run_if(needs_solve),
+                )
+                    .chain()
+                    .before(solve_constraints)
+                    .in_set(ScheduleSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                (display_dimensions, update_dimension_labels)
+                    .after(solve_constraints)
+                    .in_set(ScheduleSet::EntityUpdates),
+            )
+            // Typed like parameters, before anything else reads the keyboard
+            .add_systems(PreUpdate, handle_dimension_input.after(InputSystem))
+            .add_systems(OnExit(SketchMode::None), cancel_dimension_input)
+            .add_systems(
+                Update,
+                delete_dependent_dimensions
+                    .after(delete_dependent_lines)
+                    .after(delete_dependent_curves::<Circle>)
+                    .after(delete_dependent_curves::<Arc>)
+                    .run_if(input_just_pressed(KeyCode::KeyX))
+                    .in_set(ScheduleSet::DespawnEntities),
+            );
+    }
+}
+
+pub fn is_editing_dimension(input: Res<DimensionInput>) -> bool {
+    input.dimension.is_some()
+}
+
+// Alt plus a key dimensions the selection, then the value can be typed:
+// D distance or line length, Shift+H horizontal, Shift+V vertical, A angle, R radius, Shift+R diameter
+pub fn handle_dimension_shortcuts(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut input: ResMut<DimensionInput>,
+    mut solver: ResMut<ConstraintSolver>,
+    selected: SelectedEntities,
+    dimensions: Query<(Entity, &Dimension)>,
+    geometry: DimensionGeometry,
+) {
+    let is_shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let mut dots = Vec::new();
+    let mut lines = Vec::new();
+    let mut curves = Vec::new();
+    for (entity, is_dot, is_line) in selected.iter() {
+        if is_dot {
+            dots.push(entity);
+        } else if is_line {
+            lines.push(entity);
+        } else if geometry.circle(entity).is_some() {
+            curves.push(entity);
+        }
+    }
+    // Horizontal and vertical distances of a line are taken between its dots
+    if let [line] = lines[..]
+        && dots.is_empty()
+        && is_shift
+        && let Some((start, end)) = geometry.line_dots(line)
+    {
+        dots = vec![start, end];
+    }
+
+    let kind = if keyboard.just_pressed(KeyCode::KeyD) && !is_shift {
+        match (&dots[..], &lines[..]) {
+            ([a, b], []) => Some(DimensionKind::Distance(*a, *b)),
+            ([], [line]) => Some(DimensionKind::LineLength(*line)),
+            _ => None,
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyH) && is_shift {
+        match dots[..] {
+            [a, b] => Some(DimensionKind::HorizontalDistance(a, b)),
+            _ => None,
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyV) && is_shift {
+        match dots[..] {
+            [a, b] => Some(DimensionKind::VerticalDistance(a, b)),
+            _ => None,
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyA) && !is_shift {
+        match lines[..] {
+            [first, second] => Some(DimensionKind::Angle(first, second)),
+            _ => None,
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyR) {
+        match (&curves[..], is_shift) {
+            ([curve], false) => Some(DimensionKind::Radius(*curve)),
+            ([curve], true) => Some(DimensionKind::Diameter(*curve)),
+            _ => None,
+        }
+    } else {
+        return;
+    };
+    let Some(kind) = kind else {
+        warn!("Selection does not fit the dimension!");
+        return;
+    };
+
+    // Dimensioning the same thing again edits the existing dimension
+    let dimension = match dimensions
+        .iter()
+        .find(|(_, dimension)| dimension.kind == kind)
+    {
+        Some((dimension, _)) => dimension,
+        None => {
+            let Some(value) = geometry.measure(kind) else {
+                return;
+            };
+            // Checked by the solver in case the geometry was already fully constrained
+            solver.is_dirty = true;
+            spawn_dimension(&mut commands, kind, value)
+        }
+    };
+    input.dimension = Some(dimension);
+    input.text.clear();
+    info!("Type a value for the {:?} dimension and press Enter", kind);
+}
+
+// Values can be expressions over parameters, like `width / 4` or `30deg`
+pub fn handle_dimension_input(
+    mut commands: Commands,
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    mut events: EventReader<KeyboardInput>,
+    mut input: ResMut<DimensionInput>,
+    mut solver: ResMut<ConstraintSolver>,
+    parameters: Res<Parameters>,
+    mut dimensions: Query<&mut Dimension>,
+) {
+    if input.dimension.is_none() {
+        events.clear();
+        return;
+    }
+    let Some((entity, mut dimension)) = input.dimension.and_then(|entity| {
+        dimensions
+            .get_mut(entity)
+            .ok()
+            .map(|dimension| (entity, dimension))
+    }) else {
+        *input = DimensionInput::default();
+        return;
+    };
+
+    type_text(&mut events, &keyboard, &mut input.text);
+    let is_escape = keyboard.just_pressed(KeyCode::Escape);
+    let is_enter = keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]);
+    keyboard.clear();
+    if is_escape {
+        *input = DimensionInput::default();
+        return;
+    }
+    if !is_enter {
+        return;
+    }
+
+    // An empty value keeps the dimension as measured
+    if !input.text.is_empty() {
+        match evaluate_dimension(&parameters, dimension.kind, &input.text) {
+            Ok((value, _)) if !dimension.kind.is_valid(value) => {
+                warn!("Invalid dimension value: {:?}", input.text);
+                input.text.clear();
+                return;
+            }
+            Ok((value, is_parametric)) => {
+                commands.entity(entity).insert(Pending::Edited(
+                    dimension.value,
+                    dimension.expression.clone(),
+                ));
+                dimension.value = value;
+                dimension.expression = is_parametric.then(|| input.text.clone());
+                solver.is_dirty = true;
+            }
+            Err(error) => {
+                warn!("Invalid dimension value {:?}: {}", input.text, error);
+                input.text.clear();
+                return;
+            }
+        }
+    }
+    info!("Dimension set: {}", dimension.kind.format(dimension.value));
+    *input = DimensionInput::default();
+}
+
+// The value in the dimension's units, and whether it refers to any parameter
+pub fn evaluate_dimension(
+    parameters: &Parameters,
+    kind: DimensionKind,
+    text: &str,
+) -> Result<(f32, bool), ExpressionError> {
+    let expression = Expression::parse(text)?;
+    let quantity = parameters.evaluate(&expression)?;
+    if !quantity.fits(kind.unit()) {
+        return Err(ExpressionError::UnitMismatch);
+    }
+    Ok((quantity.value as f32, !expression.names().is_empty()))
+}
+
+// Changing a parameter re-solves every dimension whose expression refers to it
+pub fn drive_parameter_dimensions(
+    mut commands: Commands,
+    mut solver: ResMut<ConstraintSolver>,
+    parameters: Res<Parameters>,
+    mut dimensions: Query<(Entity, &mut Dimension)>,
+) {
+    for (entity, mut dimension) in dimensions.iter_mut() {
+        let Some(expression) = dimension.expression.clone() else {
+            continue;
+        };
+        match evaluate_dimension(&parameters, dimension.kind, &expression) {
+            Ok((value, _)) if !dimension.kind.is_valid(value) => {
+                warn!("Dimension {:?} evaluates to an invalid value", expression);
+            }
+            // A rejected value leaves the dimension linked but behind its parameters,
+            // until they change to something the sketch can follow
+            Ok((value, _)) if (dimension.value - value).abs() > f32::EPSILON => {
+                commands
+                    .entity(entity)
+                    .insert(Pending::Edited(dimension.value, Some(expression)));
+                dimension.value = value;
+                solver.is_dirty = true;
+            }
+            Ok(_) => {}
+            Err(error) => warn!("Dimension {:?} no longer evaluates: {}", expression, error),
+        }
+    }
+}
+
+pub fn cancel_dimension_input(mut input: ResMut<DimensionInput>) {
+    *input = DimensionInput::default();
+}
+
+// Circle radii are not dots, so they are set directly rather than solved for
+pub fn drive_circle_dimensions(dimensions: Query<&Dimension>, mut circles: Query<&mut Circle>) {
+    for dimension in dimensions.iter() {
+        let (curve, radius) = match dimension.kind {
+            DimensionKind::Radius(curve) => (curve, dimension.value),
+            DimensionKind::Diameter(curve) => (curve, dimension.value / 2.),
+            _ => continue,
+        };
+        if let Ok(mut circle) = circles.get_mut(curve)
+            && (circle.radius - radius).abs() > f32::EPSILON
+        {
+            circle.radius = radius;
+        }
+    }
+}
+
+pub fn display_dimensions(
+    mut gizmos: Gizmos,
+    input: Res<DimensionInput>,
+    picking: Res<Picking>,
+    dimensions: Query<(Entity, &Dimension, Has<Selected>)>,
+    geometry: DimensionGeometry,
+) {
+    for (entity, dimension, is_selected) in dimensions.iter() {
+        let Some(annotation) = geometry.annotation(dimension.kind) else {
+            continue;
+        };
+        let color = dimension_color(&input, &picking, entity, is_selected);
+        for strip in annotation.strips {
+            gizmos.linestrip(strip, color);
+        }
+    }
+}
+
+// Keep each label next to its dimension, and drop labels whose dimension is gone
+pub fn update_dimension_labels(
+    mut commands: Commands,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    input: Res<DimensionInput>,
+    picking: Res<Picking>,
+    dimensions: Query<(&Dimension, Has<Selected>)>,
+    mut labels: DimensionLabels,
+    geometry: DimensionGeometry,
+) {
+    let (camera, camera_transform) = *camera;
+    for (entity, label, mut text, mut node, mut text_color) in labels.iter_mut() {
+        let Ok((dimension, is_selected)) = dimensions.get(label.dimension) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let Some(annotation) = geometry.annotation(dimension.kind) else {
+            continue;
+        };
+        let Ok(position) = camera.world_to_viewport(camera_transform, annotation.label) else {
+            continue;
+        };
+        text.0 = if input.dimension == Some(label.dimension) {
+            format!("{}_", input.text)
+        } else if let Some(expression) = &dimension.expression {
+            format!(
+                "{} = {}",
+                expression,
+                dimension.kind.format(dimension.value)
+            )
+        } else {
+            dimension.kind.format(dimension.value)
+        };
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+        text_color.0 = dimension_color(&input, &picking, label.dimension, is_selected);
+    }
+}
+
+// Coloured like selected and hovered meshes, and green while its value is typed
+fn dimension_color(
+    input: &DimensionInput,
+    picking: &Picking,
+    dimension: Entity,
+    is_selected: bool,
+) -> Color {
+    let is_hovered = picking.hovered == dimension;
+    color_from_hex(match (is_selected, is_hovered) {
+        _ if input.dimension == Some(dimension) => LEAF_GREEN,
+        (true, true) => DARK_SEAFOAM,
+        (true, false) => LEAF_GREEN,
+        (false, true) => HOVER,
+        (false, false) => GOLD_YELLOW,
+    })
+}
+
+pub fn spawn_dimension(commands: &mut Commands, kind: DimensionKind, value: f32) -> Entity {
+    let dimension = commands
+        .spawn((
+            Dimension {
+                kind,
+                value,
+                expression: None,
+                is_signed: false,
+            },
+            Pending::Added,
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id();
+    commands.spawn((
+        DimensionLabel { dimension },
+        Text::new(kind.format(value)),
+        TextFont {
+            font_size: DIMENSION_FONT_SIZE,
+            ..default()
+        },
+        TextColor(color_from_hex(GOLD_YELLOW)),
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        Reloadable {
+            level: ReloadLevel::Hard,
+        },
+    ));
+    dimension
+}
+
+// Delete dimensions if any dot, line or curve they refer to has been deleted
+pub fn delete_dependent_dimensions(
+    mut commands: Commands,
+    dimensions: Query<(Entity, &Dimension)>,
+    sketched: SketchedEntities,
+) {
+    for (entity, dimension) in dimensions.iter() {
+        let is_dangling = dimension
+            .kind
+            .entities()
+            .iter()
+            .any(|referenced| !sketched.contains(*referenced));
+        if is_dangling {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+// Sketch lengths are in millimetres and angles in degrees
+const UNITS: [(&str, Unit, f64); 7] = [
+    ("mm", Unit::Length, 1.),
+    ("cm", Unit::Length, 10.),
+    ("m", Unit::Length, 1000.),
+    ("in", Unit::Length, 25.4),
+    ("ft", Unit::Length, 304.8),
+    ("deg", Unit::Angle, 1.),
+    ("rad", Unit::Angle, 180. / PI),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Length,
+    Angle,
+}
+
+// A value in sketch units, with no unit for plain numbers and ratios
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Option<Unit>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(Quantity),
+    Name(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionError {
+    Syntax(String),
+    InvalidName(String),
+    UnknownName(String),
+    // A parameter, then one that refers to it
+    InUse(String, String),
+    // Names along the loop, starting and ending with the same one
+    Cycle(Vec<String>),
+    UnitMismatch,
+    DivisionByZero,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::Syntax(message) => write!(f, "{}", message),
+            ExpressionError::InvalidName(name) => {
+                write!(f, "{:?} can not be a parameter name", name)
+            }
+            ExpressionError::UnknownName(name) => write!(f, "Unknown parameter {:?}", name),
+            ExpressionError::InUse(name, user) => write!(f, "{:?} is used by {:?}", name, user),
+            ExpressionError::Cycle(names) => {
+                write!(f, "Parameters refer to each other: {}", names.join(" -> "))
+            }
+            ExpressionError::UnitMismatch => write!(f, "Units do not match"),
+            ExpressionError::DivisionByZero => write!(f, "Division by zero"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+impl Quantity {
+    pub fn plain(value: f64) -> Self {
+        Quantity { value, unit: None }
+    }
+
+    // Plain numbers fit any unit
+    pub fn fits(&self, unit: Unit) -> bool {
+        self.unit.is_none_or(|own| own == unit)
+    }
+
+    fn apply(self, operator: Operator, other: Quantity) -> Result<Quantity, ExpressionError> {
+        let (value, unit) = match operator {
+            // Plain numbers are taken to be in sketch units already
+            Operator::Add | Operator::Subtract => {
+                let unit = match (self.unit, other.unit) {
+                    (Some(own), Some(unit)) if own != unit => {
+                        return Err(ExpressionError::UnitMismatch);
+                    }
+                    (own, unit) => own.or(unit),
+                };
+                let value = if operator == Operator::Add {
+                    self.value + other.value
+                } else {
+                    self.value - other.value
+                };
+                (value, unit)
+            }
+            // Areas and other derived units have no dimension to drive
+            Operator::Multiply => match (self.unit, other.unit) {
+                (Some(_), Some(_)) => return Err(ExpressionError::UnitMismatch),
+                (unit, None) | (None, unit) => (self.value * other.value, unit),
+            },
+            Operator::Divide => {
+                if other.value == 0. {
+                    return Err(ExpressionError::DivisionByZero);
+                }
+                let unit = match (self.unit, other.unit) {
+                    (unit, None) => unit,
+                    (Some(own), Some(unit)) if own == unit => None,
+                    _ => return Err(ExpressionError::UnitMismatch),
+                };
+                (self.value / other.value, unit)
+            }
+        };
+        Ok(Quantity { value, unit })
+    }
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Expression, ExpressionError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            next: 0,
+        };
+        let expression = parser.sum()?;
+        if parser.next < tokens.len() {
+            return Err(ExpressionError::Syntax(format!(
+                "Unexpected {:?}",
+                tokens[parser.next]
+            )));
+        }
+        Ok(expression)
+    }
+
+    // Parameter names the expression refers to
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            Expression::Number(_) => Vec::new(),
+            Expression::Name(name) => vec![name.as_str()],
+            Expression::Negate(inner) => inner.names(),
+            Expression::Binary(left, _, right) => {
+                let mut names = left.names();
+                names.extend(right.names());
+                names
+            }
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        lookup: &mut impl FnMut(&str) -> Result<Quantity, ExpressionError>,
+    ) -> Result<Quantity, ExpressionError> {
+        match self {
+            Expression::Number(quantity) => Ok(*quantity),
+            Expression::Name(name) => lookup(name),
+            Expression::Negate(inner) => {
+                let quantity = inner.evaluate(lookup)?;
+                Ok(Quantity {
+                    value: -quantity.value,
+                    ..quantity
+                })
+            }
+            Expression::Binary(left, operator, right) => {
+                let left = left.evaluate(lookup)?;
+                left.apply(*operator, right.evaluate(lookup)?)
+            }
+        }
+    }
+}
+
+// Names must start with a letter or underscore and can not be units
+pub fn check_name(name: &str) -> Result<(), ExpressionError> {
+    let mut characters = name.chars();
+    let is_identifier = characters
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && characters.all(|character| character.is_alphanumeric() || character == '_');
+    if !is_identifier || unit(name).is_some() {
+        return Err(ExpressionError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+fn unit(name: &str) -> Option<(Unit, f64)> {
+    UNITS
+        .iter()
+        .find(|(symbol, _, _)| *symbol == name)
+        .map(|(_, unit, scale)| (*unit, *scale))
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ExpressionError> {
+    let characters: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < characters.len() {
+        let character = characters[i];
+        let start = i;
+        i += 1;
+        let token = match character {
+            ' ' | '\t' => continue,
+            '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Subtract),
+            '*' => Token::Operator(Operator::Multiply),
+            '/' => Token::Operator(Operator::Divide),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '0'..='9' | '.' => {
+                while i < characters.len()
+                    && (characters[i].is_ascii_digit() || characters[i] == '.')
+                {
+                    i += 1;
+                }
+                let number: String = characters[start..i].iter().collect();
+                let value = number
+                    .parse()
+                    .map_err(|_| ExpressionError::Syntax(format!("Invalid number {:?}", number)))?;
+                Token::Number(value)
+            }
+            _ if character.is_alphabetic() || character == '_' => {
+                while i < characters.len()
+                    && (characters[i].is_alphanumeric() || characters[i] == '_')
+                {
+                    i += 1;
+                }
+                Token::Name(characters[start..i].iter().collect())
+            }
+            _ => {
+                return Err(ExpressionError::Syntax(format!(
+                    "Unexpected {:?}",
+                    character
+                )));
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// Recursive descent over sums of products of signed numbers, names and parentheses
+struct Parser<'a> {
+    tokens: &'a [Token],
+    next: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn take(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.next);
+        self.next += 1;
+        token
+    }
+
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        let mut expression = self.product()?;
+        while let Some(Token::Operator(operator @ (Operator::Add | Operator::Subtract))) =
+            self.peek().cloned()
+        {
+            self.next += 1;
+            expression =
+                Expression::Binary(Box::new(expression), operator, Box::new(self.product()?));
+        }
+        Ok(expression)
+    }
+
+    fn product(&mut self) -> Result<Expression, ExpressionError> {
+        let mut expression = self.factor()?;
+        while let Some(Token::Operator(operator @ (Operator::Multiply | Operator::Divide))) =
+            self.peek().cloned()
+        {
+            self.next += 1;
+            expression =
+                Expression::Binary(Box::new(expression), operator, Box::new(self.factor()?));
+        }
+        Ok(expression)
+    }
+
+    fn factor(&mut self) -> Result<Expression, ExpressionError> {
+        match self.take().cloned() {
+            Some(Token::Operator(Operator::Subtract)) => {
+                Ok(Expression::Negate(Box::new(self.factor()?)))
+            }
+            Some(Token::Number(value)) => {
+                // A unit right after a number scales it to sketch units
+                if let Some(Token::Name(name)) = self.peek()
+                    && let Some((unit, scale)) = unit(name)
+                {
+                    self.next += 1;
+                    return Ok(Expression::Number(Quantity {
+                        value: value * scale,
+                        unit: Some(unit),
+                    }));
+                }
+                Ok(Expression::Number(Quantity::plain(value)))
+            }
+            Some(Token::Name(name)) => Ok(Expression::Name(name)),
+            Some(Token::Open) => {
+                let expression = self.sum()?;
+                match self.take() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => Err(ExpressionError::Syntax(
+                        "Missing closing parenthesis".to_string(),
+                    )),
+                }
+            }
+            Some(token) => Err(ExpressionError::Syntax(format!("Unexpected {:?}", token))),
+            None => Err(ExpressionError::Syntax(
+                "Unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str) -> Result<Quantity, ExpressionError> {
+        Expression::parse(text)?
+            .evaluate(&mut |name| Err(ExpressionError::UnknownName(name.to_string())))
+    }
+
+    fn value(text: &str) -> f64 {
+        evaluate(text).unwrap().value
+    }
+
+    #[test]
+    fn products_bind_tighter_than_sums() {
+        assert_eq!(value("1 + 2 * 3"), 7.);
+        assert_eq!(value("(1 + 2) * 3"), 9.);
+        assert_eq!(value("8 - 4 - 2"), 2.);
+        assert_eq!(value("8 / 4 / 2"), 1.);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(value("-3"), -3.);
+        assert_eq!(value("2 * -3"), -6.);
+        assert_eq!(value("--3"), 3.);
+        assert_eq!(value("-(1 + 2)"), -3.);
+    }
+
+    #[test]
+    fn units_scale_to_sketch_units() {
+        assert_eq!(
+            evaluate("5mm"),
+            Ok(Quantity {
+                value: 5.,
+                unit: Some(Unit::Length)
+            })
+        );
+        assert!((value("1in") - 25.4).abs() < 1e-9);
+        assert!((value("2 cm + 1mm") - 21.).abs() < 1e-9);
+        assert_eq!(evaluate("90deg").unwrap().unit, Some(Unit::Angle));
+        assert!((value("1rad") - 180. / PI).abs() < 1e-9);
+        // Plain numbers take the unit of what they are added to
+        assert_eq!(evaluate("1in + 1").unwrap().unit, Some(Unit::Length));
+        // Ratios of the same unit are plain
+        assert_eq!(evaluate("1in / 1mm").unwrap().unit, None);
+    }
+
+    #[test]
+    fn unit_mismatch() {
+        assert_eq!(evaluate("1mm + 1deg"), Err(ExpressionError::UnitMismatch));
+        assert_eq!(evaluate("1mm * 1mm"), Err(ExpressionError::UnitMismatch));
+        assert_eq!(evaluate("1 / 1mm"), Err(ExpressionError::UnitMismatch));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(evaluate("1 / 0"), Err(ExpressionError::DivisionByZero));
+        assert_eq!(
+            evaluate("1mm / (2 - 2)"),
+            Err(ExpressionError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(matches!(evaluate("1 +"), Err(ExpressionError::Syntax(_))));
+        assert!(matches!(evaluate("(1"), Err(ExpressionError::Syntax(_))));
+        assert!(matches!(evaluate("1 2"), Err(ExpressionError::Syntax(_))));
+        assert!(matches!(evaluate("1 % 2"), Err(ExpressionError::Syntax(_))));
+    }
+
+    #[test]
+    fn names() {
+        let expression = Expression::parse("width / 2 + margin").unwrap();
+        assert_eq!(expression.names(), vec!["width", "margin"]);
+        assert!(check_name("width_2").is_ok());
+        assert!(check_name("2width").is_err());
+        assert!(check_name("mm").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::assets::materials::ChangingMaterial;
+use crate::sketching::arc::Arc;
+use crate::sketching::circle::Circle;
+use crate::sketching::curve::{DotTransforms, SketchCurve};
+use crate::sketching::dot::Dot;
+use crate::sketching::ellipse::{Ellipse, EllipticalArc};
+use crate::sketching::line::Line;
+use crate::sketching::spline::Spline;
+
+use super::solver::SketchConstraints;
+
+type DotStates<'w, 's> = Query<'w, 's, (Entity, Option<&'static ConstraintState>), With<Dot>>;
+type LineStates<'w, 's> = Query<'w, 's, (Entity, &'static Line, Option<&'static ConstraintState>)>;
+type CurveStates<'w, 's, T> = Query<'w, 's, (Entity, &'static T, Option<&'static ConstraintState>)>;
+
+// How far the constraints pin a dot, line or curve down.
+// Ordered so a line or curve takes the worst state of its dots.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConstraintState {
+    Full,
+    Under,
+    Over,
+}
+
+// Curves other than lines, which are shaped by their dots
+#[derive(SystemParam)]
+pub struct SketchCurveStates<'w, 's> {
+    circles: CurveStates<'w, 's, Circle>,
+    arcs: CurveStates<'w, 's, Arc>,
+    splines: CurveStates<'w, 's, Spline>,
+    ellipses: CurveStates<'w, 's, Ellipse>,
+    elliptical_arcs: CurveStates<'w, 's, EllipticalArc>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SketchFreedom {
+    pub degrees_of_freedom: usize,
+}
+
+pub fn analyze_freedom(
+    mut commands: Commands,
+    mut freedom: ResMut<SketchFreedom>,
+    constraints: SketchConstraints,
+    dots: DotTransforms,
+    dot_states: DotStates,
+    line_states: LineStates,
+    curve_states: SketchCurveStates,
+) {
+    let mut sketch = constraints.build(&dots);
+    sketch.add_dots(dot_states.iter().map(|(dot, _)| dot), &dots);
+    let analysis = sketch.system.analyze();
+
+    let mut states: Vec<ConstraintState> = analysis
+        .is_free
+        .iter()
+        .map(|is_free| {
+            if *is_free {
+                ConstraintState::Under
+            } else {
+                ConstraintState::Full
+            }
+        })
+        .collect();
+    for equation in analysis.circuits.iter().flatten() {
+        for point in sketch.system.equations[*equation].points() {
+            states[point] = ConstraintState::Over;
+        }
+    }
+    let dot_state = |dot: Entity| {
+        sketch
+            .index(dot)
+            .map_or(ConstraintState::Under, |index| states[index])
+    };
+
+    for (dot, state) in dot_states.iter() {
+        set_constraint_state(&mut commands, dot, state, dot_state(dot));
+    }
+    for (line, Line { start, end }, state) in line_states.iter() {
+        let next = dot_state(*start).max(dot_state(*end));
+        set_constraint_state(&mut commands, line, state, next);
+    }
+    set_curve_states(&mut commands, &curve_states.circles, dot_state);
+    set_curve_states(&mut commands, &curve_states.arcs, dot_state);
+    set_curve_states(&mut commands, &curve_states.splines, dot_state);
+    set_curve_states(&mut commands, &curve_states.ellipses, dot_state);
+    set_curve_states(&mut commands, &curve_states.elliptical_arcs, dot_state);
+
+    if freedom.degrees_of_freedom != analysis.degrees_of_freedom {
+        freedom.degrees_of_freedom = analysis.degrees_of_freedom;
+        info!("Sketch degrees of freedom: {}", freedom.degrees_of_freedom);
+    }
+}
+
+fn set_curve_states<T: SketchCurve>(
+    commands: &mut Commands,
+    curves: &CurveStates<T>,
+    dot_state: impl Fn(Entity) -> ConstraintState,
+) {
+    for (entity, curve, state) in curves.iter() {
+        let next = curve
+            .dots()
+            .into_iter()
+            .map(&dot_state)
+            .max()
+            .unwrap_or(ConstraintState::Under);
+        set_constraint_state(commands, entity, state, next);
+    }
+}
+
+fn set_constraint_state(
+    commands: &mut Commands,
+    entity: Entity,
+    state: Option<&ConstraintState>,
+    next: ConstraintState,
+) {
+    if state != Some(&next) {
+        commands.entity(entity).insert((next, ChangingMaterial));
+    }
+}
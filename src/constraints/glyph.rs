@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+use crate::sketching::dot::Dot;
+use crate::sketching::line::Line;
+use crate::sketching::sketch::update_moving_transforms;
+
+use super::constraint::Constraint;
+use super::diagnostic::handle_constraint_diagnostics;
+use super::solver::solve_constraints;
+
+// Glyphs are square plates in world units, so they scale with the sketch
+const GLYPH_SIZE: f32 = 0.2;
+// Distance from the constrained dot or line to the first glyph
+const GLYPH_OFFSET: f32 = 0.18;
+// Further glyphs on the same dot or line line up next to the first
+const GLYPH_SPACING: f32 = 0.24;
+// Also used by the inference preview, so it matches the glyphs it places
+pub const GLYPH_FONT_SIZE: f32 = 12.;
+
+type GlyphTransforms<'w, 's> =
+    Query<'w, 's, (&'static Constraint, &'static mut Transform), Without<Dot>>;
+type GlyphDotTransforms<'w, 's> =
+    Query<'w, 's, &'static Transform, (With<Dot>, Without<Constraint>)>;
+type ConstraintLabels<'w, 's> =
+    Query<'w, 's, (Entity, &'static ConstraintLabel, &'static mut Node)>;
+
+// Screen space text drawn over the glyph plate of a constraint
+#[derive(Component, Debug)]
+pub struct ConstraintLabel {
+    pub constraint: Entity,
+}
+
+#[derive(Resource, Debug)]
+pub struct GlyphMeshHandle(pub Handle<Mesh>);
+
+impl UIMaterialProvider for Constraint {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.glyph.clone()
+    }
+}
+
+pub struct GlyphPlugin;
+
+impl Plugin for GlyphPlugin {
+    fn build(&self, app: &mut App) {
+        let glyph_mesh_handle = app
+            .world_mut()
+            .resource_mut::<Assets<Mesh>>()
+            .add(Rectangle::new(GLYPH_SIZE, GLYPH_SIZE));
+        app.insert_resource(GlyphMeshHandle(glyph_mesh_handle))
+            .add_systems(
+                Update,
+                (
+                    spawn_constraint_glyphs,
+                    update_glyph_transforms,
+                    update_constraint_labels,
+                )
+                    .chain()
+                    .after(handle_constraint_diagnostics)
+                    .after(solve_constraints)
+                    .after(update_moving_transforms)
+                    .in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+// New constraints get a plate to hover and select them by, and a label naming them
+pub fn spawn_constraint_glyphs(
+    mut commands: Commands,
+    glyph_mesh: Res<GlyphMeshHandle>,
+    ui_materials: Res<UIMaterials>,
+    constraints: Query<(Entity, &Constraint), Added<Constraint>>,
+) {
+    for (entity, constraint) in constraints.iter() {
+        commands.entity(entity).try_insert((
+            Mesh3d(glyph_mesh.0.clone()),
+            MeshMaterial3d(ui_materials.glyph.clone()),
+        ));
+        commands.spawn((
+            ConstraintLabel { constraint: entity },
+            Text::new(constraint.glyph()),
+            TextFont {
+                font_size: GLYPH_FONT_SIZE,
+                ..default()
+            },
+            TextColor(color_from_hex(LINE)),
+            TextLayout::new_with_justify(JustifyText::Center),
+            Node {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ));
+    }
+}
+
+// Place glyphs beside the first dot or line they constrain
+pub fn update_glyph_transforms(
+    mut glyphs: GlyphTransforms,
+    lines: Query<&Line>,
+    dots: GlyphDotTransforms,
+) {
+    let mut stacked: HashMap<Entity, f32> = HashMap::new();
+    for (constraint, mut transform) in glyphs.iter_mut() {
+        let anchor = constraint.entities()[0];
+        let (position, along) = if let Ok(line) = lines.get(anchor) {
+            let (Ok(start), Ok(end)) = (dots.get(line.start), dots.get(line.end)) else {
+                continue;
+            };
+            let direction = (end.translation - start.translation).normalize_or(Vec3::X);
+            let normal = Vec3::new(-direction.y, direction.x, 0.);
+            let middle = (start.translation + end.translation) / 2.;
+            (middle + normal * GLYPH_OFFSET, direction)
+        } else if let Ok(dot) = dots.get(anchor) {
+            (
+                dot.translation + Vec3::new(GLYPH_OFFSET, -GLYPH_OFFSET, 0.),
+                Vec3::X,
+            )
+        } else {
+            continue;
+        };
+        let count = stacked.entry(anchor).or_default();
+        transform.translation = position + along * *count * GLYPH_SPACING;
+        *count += 1.;
+    }
+}
+
+// Keep each label over its glyph, and drop labels whose constraint is gone
+pub fn update_constraint_labels(
+    mut commands: Commands,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    glyphs: Query<&Transform, With<Constraint>>,
+    mut labels: ConstraintLabels,
+) {
+    let (camera, camera_transform) = *camera;
+    for (entity, label, mut node) in labels.iter_mut() {
+        let Ok(glyph) = glyphs.get(label.constraint) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let corner = glyph.translation + Vec3::new(-GLYPH_SIZE, GLYPH_SIZE, 0.) / 2.;
+        let (Ok(center), Ok(corner)) = (
+            camera.world_to_viewport(camera_transform, glyph.translation),
+            camera.world_to_viewport(camera_transform, corner),
+        ) else {
+            continue;
+        };
+        node.left = Val::Px(corner.x);
+        node.width = Val::Px((center.x - corner.x) * 2.);
+        node.top = Val::Px(center.y - GLYPH_FONT_SIZE * 0.6);
+    }
+}
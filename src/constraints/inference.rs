@@ -0,0 +1,196 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::cursor::Picking;
+use crate::schedule::ScheduleSet;
+use crate::sketching::arc::Arc;
+use crate::sketching::circle::Circle;
+use crate::sketching::coordinate::is_placing_point;
+use crate::sketching::curve::DotTransforms;
+use crate::sketching::dot::Dot;
+use crate::sketching::line::{Line, clear_redundant, handle_move_current_line};
+use crate::sketching::sketch::{Current, SketchMode};
+
+use super::constraint::{Constraint, spawn_constraint};
+use super::glyph::GLYPH_FONT_SIZE;
+use super::solver::ConstraintSolver;
+
+// Largest angle off horizontal, vertical, parallel or perpendicular that is still inferred, about 3 degrees
+const INFERENCE_ANGLE: f32 = 0.05;
+// The preview glyph is screen space text, offset from the mouse in pixels
+const INFERENCE_GLYPH_OFFSET: Vec2 = Vec2::new(14., 14.);
+
+type SketchedCurves<'w, 's> = Query<'w, 's, (), Or<(With<Line>, With<Circle>, With<Arc>)>>;
+type SketchedDotsAndLines<'w, 's> = Query<'w, 's, (), Or<(With<Dot>, With<Line>)>>;
+
+// Constraints the pending segment gets if the next click finishes it
+#[derive(Resource, Debug, Default)]
+pub struct Inference {
+    pub constraints: Vec<Constraint>,
+    // Curve under the cursor, which the line tool puts the clicked dot on by itself
+    pub on_curve: Option<Entity>,
+}
+
+// Preview of the inferred constraints next to the cursor
+#[derive(Component)]
+pub struct InferenceGlyph;
+
+pub struct InferencePlugin;
+
+impl Plugin for InferencePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Inference::default())
+            .add_systems(Startup, spawn_inference_glyph)
+            .add_systems(OnExit(SketchMode::Line), reset_inference)
+            .add_systems(
+                Update,
+                (
+                    apply_inferred_constraints
+                        .run_if(in_state(SketchMode::Line).and(is_placing_point)),
+                    infer_line_constraints.run_if(in_state(SketchMode::Line)),
+                    update_inference_glyph,
+                )
+                    .chain()
+                    .after(clear_redundant)
+                    .after(handle_move_current_line)
+                    .in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+fn spawn_inference_glyph(mut commands: Commands) {
+    commands.spawn((
+        InferenceGlyph,
+        Text::default(),
+        TextFont {
+            font_size: GLYPH_FONT_SIZE,
+            ..default()
+        },
+        TextColor(color_from_hex(HOVER)),
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+    ));
+}
+
+pub fn reset_inference(mut inference: ResMut<Inference>) {
+    *inference = Inference::default();
+}
+
+// Holding Ctrl sketches without inferring anything
+pub fn infer_line_constraints(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    current: Res<Current>,
+    picking: Res<Picking>,
+    mut inference: ResMut<Inference>,
+    lines: Query<&Line>,
+    curves: SketchedCurves,
+    dots: DotTransforms,
+) {
+    *inference = Inference::default();
+    if keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    if curves.contains(picking.hovered) {
+        inference.on_curve = Some(picking.hovered);
+    }
+    let hovered_line = lines.get(picking.hovered).ok();
+
+    let Some(line_entity) = current.lines.first().copied() else {
+        return;
+    };
+    let Ok(line) = lines.get(line_entity) else {
+        return;
+    };
+    let Some(direction) = line_direction(line, &dots) else {
+        return;
+    };
+
+    // Lines through the start of the segment would only fold it onto them
+    let hovered_line =
+        hovered_line.filter(|hovered| hovered.start != line.start && hovered.end != line.start);
+
+    if acute_angle(direction, Vec2::X) < INFERENCE_ANGLE {
+        inference
+            .constraints
+            .push(Constraint::Horizontal(line_entity));
+    } else if acute_angle(direction, Vec2::Y) < INFERENCE_ANGLE {
+        inference
+            .constraints
+            .push(Constraint::Vertical(line_entity));
+    } else if let Some(reference) = hovered_line.and_then(|hovered| line_direction(hovered, &dots))
+    {
+        let angle = acute_angle(direction, reference);
+        if angle < INFERENCE_ANGLE {
+            inference
+                .constraints
+                .push(Constraint::Parallel(line_entity, picking.hovered));
+        } else if (angle - FRAC_PI_2).abs() < INFERENCE_ANGLE {
+            inference
+                .constraints
+                .push(Constraint::Perpendicular(line_entity, picking.hovered));
+        }
+    }
+}
+
+fn line_direction(line: &Line, dots: &DotTransforms) -> Option<Vec2> {
+    let start = dots.get(line.start).ok()?.translation;
+    let end = dots.get(line.end).ok()?.translation;
+    let direction = (end - start).truncate();
+    (direction.length() > f32::EPSILON).then_some(direction)
+}
+
+// Angle between two undirected lines, from 0 to a right angle
+fn acute_angle(direction: Vec2, reference: Vec2) -> f32 {
+    let angle = direction.angle_to(reference).abs();
+    angle.min(PI - angle)
+}
+
+// Inferences were worked out for the cursor before the click, so they refer to the finished segment
+pub fn apply_inferred_constraints(
+    mut commands: Commands,
+    mut inference: ResMut<Inference>,
+    mut solver: ResMut<ConstraintSolver>,
+    sketched: SketchedDotsAndLines,
+) {
+    for constraint in std::mem::take(&mut inference.constraints) {
+        // The click may have merged the segment away
+        if constraint
+            .entities()
+            .iter()
+            .all(|entity| sketched.contains(*entity))
+        {
+            spawn_constraint(&mut commands, constraint);
+            solver.is_dirty = true;
+        }
+    }
+}
+
+pub fn update_inference_glyph(
+    inference: Res<Inference>,
+    windows: Query<&Window>,
+    glyph: Single<(&mut Text, &mut Node), With<InferenceGlyph>>,
+) {
+    let (mut text, mut node) = glyph.into_inner();
+    let glyphs: Vec<&str> = inference
+        .constraints
+        .iter()
+        .map(Constraint::glyph)
+        .chain(inference.on_curve.map(|_| "-o-"))
+        .collect();
+    text.0 = glyphs.join(" ");
+
+    let Some(position) = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    node.left = Val::Px(position.x + INFERENCE_GLYPH_OFFSET.x);
+    node.top = Val::Px(position.y + INFERENCE_GLYPH_OFFSET.y);
+}
@@ -0,0 +1,10 @@
+pub mod constraint;
+pub mod diagnostic;
+pub mod dimension;
+pub mod expression;
+pub mod freedom;
+pub mod glyph;
+pub mod inference;
+pub mod parameter;
+pub mod simulation;
+pub mod solver;
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::InputSystem;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::schedule::ScheduleSet;
+use crate::sketching::sketch::SketchMode;
+
+use super::constraint::is_alt_pressed;
+use super::dimension::{Dimension, DimensionInput};
+use super::expression::{Expression, ExpressionError, Quantity, check_name};
+
+const PARAMETER_FONT_SIZE: f32 = 14.;
+const PARAMETER_MARGIN: f32 = 12.;
+
+// Named value that dimensions can refer to by expression
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub text: String,
+    pub value: Quantity,
+    expression: Expression,
+}
+
+// Sketch level variable table, always evaluated in dependency order
+#[derive(Resource, Debug, Default)]
+pub struct Parameters {
+    pub entries: Vec<Parameter>,
+}
+
+// Line being typed into the table, like `width = 40mm`
+#[derive(Resource, Debug, Default)]
+pub struct ParameterInput {
+    pub is_open: bool,
+    pub text: String,
+}
+
+#[derive(Component)]
+pub struct ParameterTable;
+
+impl Parameters {
+    // Defining a name again replaces it, and nothing changes if the table would no longer evaluate
+    pub fn define(&mut self, name: &str, text: &str) -> Result<(), ExpressionError> {
+        check_name(name)?;
+        let parameter = Parameter {
+            name: name.to_string(),
+            text: text.to_string(),
+            value: Quantity::plain(0.),
+            expression: Expression::parse(text)?,
+        };
+        let mut entries = self.entries.clone();
+        match entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => *entry = parameter,
+            None => entries.push(parameter),
+        }
+        self.entries = evaluate_all(entries)?;
+        Ok(())
+    }
+
+    // Parameters other parameters or dimension expressions still refer to can not be removed
+    pub fn remove<'a>(
+        &mut self,
+        name: &str,
+        dimensions: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), ExpressionError> {
+        if !self.entries.iter().any(|entry| entry.name == name) {
+            return Err(ExpressionError::UnknownName(name.to_string()));
+        }
+        if let Some(user) = self
+            .entries
+            .iter()
+            .find(|entry| entry.expression.names().contains(&name))
+        {
+            return Err(ExpressionError::InUse(name.to_string(), user.name.clone()));
+        }
+        if let Some(text) = dimensions.into_iter().find(|text| {
+            Expression::parse(text).is_ok_and(|expression| expression.names().contains(&name))
+        }) {
+            return Err(ExpressionError::InUse(name.to_string(), text.to_string()));
+        }
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| entry.name != name)
+            .cloned()
+            .collect();
+        self.entries = evaluate_all(entries)?;
+        Ok(())
+    }
+
+    pub fn evaluate(&self, expression: &Expression) -> Result<Quantity, ExpressionError> {
+        expression.evaluate(&mut |name| {
+            self.entries
+                .iter()
+                .find(|entry| entry.name == name)
+                .map(|entry| entry.value)
+                .ok_or_else(|| ExpressionError::UnknownName(name.to_string()))
+        })
+    }
+}
+
+fn evaluate_all(mut entries: Vec<Parameter>) -> Result<Vec<Parameter>, ExpressionError> {
+    let mut values = HashMap::new();
+    for entry in &entries {
+        value_of(&entry.name, &entries, &mut values, &mut Vec::new())?;
+    }
+    for entry in &mut entries {
+        entry.value = values[&entry.name];
+    }
+    Ok(entries)
+}
+
+// Depth first, so meeting a name again before its value is known closes a loop
+fn value_of(
+    name: &str,
+    entries: &[Parameter],
+    values: &mut HashMap<String, Quantity>,
+    visiting: &mut Vec<String>,
+) -> Result<Quantity, ExpressionError> {
+    if let Some(value) = values.get(name) {
+        return Ok(*value);
+    }
+    if let Some(start) = visiting.iter().position(|visited| visited == name) {
+        let mut cycle = visiting[start..].to_vec();
+        cycle.push(name.to_string());
+        return Err(ExpressionError::Cycle(cycle));
+    }
+    let entry = entries
+        .iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| ExpressionError::UnknownName(name.to_string()))?;
+    visiting.push(name.to_string());
+    let value = entry
+        .expression
+        .evaluate(&mut |other| value_of(other, entries, values, visiting))?;
+    visiting.pop();
+    values.insert(name.to_string(), value);
+    Ok(value)
+}
+
+pub struct ParameterPlugin;
+
+impl Plugin for ParameterPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Parameters::default())
+            .insert_resource(ParameterInput::default())
+            .add_systems(Startup, spawn_parameter_table)
+            // Typing runs before anything else reads the keyboard, so letters do not trigger shortcuts
+            .add_systems(PreUpdate, handle_parameter_input.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    open_parameter_input.run_if(
+                        in_state(SketchMode::None)
+                            .and(is_alt_pressed)
+                            .and(input_just_pressed(KeyCode::KeyN)),
+                    ),
+                    update_parameter_table.run_if(
+                        resource_changed::<Parameters>.or(resource_changed::<ParameterInput>),
+                    ),
+                )
+                    .chain()
+                    .in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+fn spawn_parameter_table(mut commands: Commands) {
+    commands.spawn((
+        ParameterTable,
+        Text::default(),
+        TextFont {
+            font_size: PARAMETER_FONT_SIZE,
+            ..default()
+        },
+        TextColor(color_from_hex(GOLD_YELLOW)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(PARAMETER_MARGIN),
+            right: Val::Px(PARAMETER_MARGIN),
+            ..default()
+        },
+    ));
+}
+
+// Alt+N opens a line to type `name = expression` into, or `name =` to remove the parameter
+pub fn open_parameter_input(
+    mut input: ResMut<ParameterInput>,
+    mut dimension_input: ResMut<DimensionInput>,
+) {
+    *dimension_input = DimensionInput::default();
+    input.is_open = true;
+    input.text.clear();
+}
+
+pub fn handle_parameter_input(
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    mut events: EventReader<KeyboardInput>,
+    mut input: ResMut<ParameterInput>,
+    mut parameters: ResMut<Parameters>,
+    dimensions: Query<&Dimension>,
+) {
+    if !input.is_open {
+        events.clear();
+        return;
+    }
+    type_text(&mut events, &keyboard, &mut input.text);
+    if keyboard.just_pressed(KeyCode::Escape) {
+        *input = ParameterInput::default();
+    } else if keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        let expressions = dimensions
+            .iter()
+            .filter_map(|dimension| dimension.expression.as_deref());
+        match submit_parameter(&mut parameters, &input.text, expressions) {
+            Ok(()) => *input = ParameterInput::default(),
+            // The line stays open to be fixed
+            Err(error) => warn!("Invalid parameter {:?}: {}", input.text, error),
+        }
+    }
+    keyboard.clear();
+}
+
+fn submit_parameter<'a>(
+    parameters: &mut Parameters,
+    line: &str,
+    dimensions: impl IntoIterator<Item = &'a str>,
+) -> Result<(), ExpressionError> {
+    let Some((name, text)) = line.split_once('=') else {
+        return Err(ExpressionError::Syntax(
+            "Expected name = expression".to_string(),
+        ));
+    };
+    let (name, text) = (name.trim(), text.trim());
+    if text.is_empty() {
+        parameters.remove(name, dimensions)
+    } else {
+        parameters.define(name, text)
+    }
+}
+
+// Characters typed this frame, with Alt combinations left to shortcuts
+pub fn type_text(
+    events: &mut EventReader<KeyboardInput>,
+    keyboard: &ButtonInput<KeyCode>,
+    text: &mut String,
+) {
+    let is_alt = keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    for event in events.read() {
+        if !event.state.is_pressed() || is_alt {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(characters) => text.push_str(characters),
+            Key::Space => text.push(' '),
+            Key::Backspace => {
+                text.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn update_parameter_table(
+    parameters: Res<Parameters>,
+    input: Res<ParameterInput>,
+    table: Single<&mut Text, With<ParameterTable>>,
+) {
+    let mut lines: Vec<String> = parameters
+        .entries
+        .iter()
+        .map(|entry| {
+            let value = format!("{:.2}", entry.value.value);
+            if entry.text.parse::<f64>().is_ok() {
+                format!("{} = {}", entry.name, entry.text)
+            } else {
+                format!("{} = {}  ({})", entry.name, entry.text, value)
+            }
+        })
+        .collect();
+    if input.is_open {
+        lines.push(format!("> {}_", input.text));
+    }
+    table.into_inner().0 = lines.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(definitions: &[(&str, &str)]) -> Parameters {
+        let mut parameters = Parameters::default();
+        for (name, text) in definitions {
+            parameters.define(name, text).unwrap();
+        }
+        parameters
+    }
+
+    fn value(parameters: &Parameters, name: &str) -> f64 {
+        parameters
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .unwrap()
+            .value
+            .value
+    }
+
+    #[test]
+    fn parameters_follow_each_other() {
+        let mut parameters = parameters(&[("width", "40mm"), ("half", "width / 2")]);
+        assert_eq!(value(&parameters, "half"), 20.);
+        parameters.define("width", "1in").unwrap();
+        assert!((value(&parameters, "half") - 12.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut parameters = parameters(&[("a", "1"), ("b", "a")]);
+        assert_eq!(
+            parameters.define("a", "b"),
+            Err(ExpressionError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ]))
+        );
+        // The table is left as it was
+        assert_eq!(value(&parameters, "a"), 1.);
+        assert!(matches!(
+            parameters.define("c", "c + 1"),
+            Err(ExpressionError::Cycle(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let mut parameters = Parameters::default();
+        assert_eq!(
+            parameters.define("a", "b"),
+            Err(ExpressionError::UnknownName("b".to_string()))
+        );
+        assert!(parameters.entries.is_empty());
+    }
+
+    #[test]
+    fn used_parameters_can_not_be_removed() {
+        let mut parameters = parameters(&[("a", "1"), ("b", "a * 2"), ("c", "3")]);
+        assert_eq!(
+            parameters.remove("a", []),
+            Err(ExpressionError::InUse("a".to_string(), "b".to_string()))
+        );
+        assert_eq!(
+            parameters.remove("c", ["c / 2"]),
+            Err(ExpressionError::InUse("c".to_string(), "c / 2".to_string()))
+        );
+        assert_eq!(parameters.remove("b", ["c / 2"]), Ok(()));
+        assert_eq!(parameters.remove("a", []), Ok(()));
+        assert_eq!(
+            parameters.remove("a", []),
+            Err(ExpressionError::UnknownName("a".to_string()))
+        );
+    }
+}
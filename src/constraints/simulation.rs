@@ -0,0 +1,275 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::assets::visibility::MESH_VISIBILITY;
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+use crate::sketching::construction::Construction;
+use crate::sketching::curve::{DotTransforms, curve_mesh};
+use crate::sketching::dot::Dot;
+use crate::sketching::selection::Selected;
+
+use super::diagnostic::{Pending, handle_constraint_diagnostics};
+use super::dimension::{
+    Dimension, DimensionGeometry, DimensionInput, DimensionKind, evaluate_dimension,
+    handle_dimension_input, is_editing_dimension,
+};
+use super::parameter::Parameters;
+use super::solver::{ConstraintSolver, solve_constraints};
+
+// Frames from the start of the range to its end
+const SIMULATION_STEPS: u32 = 120;
+// Smallest movement of a traced dot that adds to its path
+const MIN_TRACE_STEP: f32 = 1e-4;
+
+type SelectedDots<'w, 's> = Query<'w, 's, Entity, (With<Dot>, With<Selected>)>;
+
+// Path a dot took during a simulation, kept as construction geometry without dots of its own,
+// so it can be picked and deleted but adds nothing to the solver
+#[derive(Component, Debug)]
+pub struct Trace(pub Vec<Vec3>);
+
+impl UIMaterialProvider for Trace {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.construction.clone()
+    }
+}
+
+// A driving dimension swept over a range, with the paths of the dots being traced
+#[derive(Resource, Debug, Default)]
+pub struct Simulation {
+    pub dimension: Option<Entity>,
+    pub from: f32,
+    pub to: f32,
+    // Value and parameter expression the dimension had before it was swept
+    pub initial: f32,
+    pub expression: Option<String>,
+    pub step: u32,
+    // Value last given to the dimension, to notice when the solver reverted it
+    pub value: f32,
+    pub traces: Vec<(Entity, Vec<Vec3>)>,
+    pub is_finished: bool,
+}
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Simulation::default())
+            .add_systems(
+                PreUpdate,
+                start_simulation
+                    .after(InputSystem)
+                    .before(handle_dimension_input)
+                    .run_if(is_editing_dimension),
+            )
+            .add_systems(
+                Update,
+                (
+                    drive_simulation.before(solve_constraints),
+                    (
+                        record_simulation,
+                        (restore_simulated_dimension, finish_simulation)
+                            .chain()
+                            .run_if(is_simulation_finished),
+                    )
+                        .chain()
+                        .after(handle_constraint_diagnostics),
+                )
+                    .run_if(is_simulating)
+                    .in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+pub fn is_simulating(simulation: Res<Simulation>) -> bool {
+    simulation.dimension.is_some()
+}
+
+fn is_simulation_finished(simulation: Res<Simulation>) -> bool {
+    simulation.is_finished
+}
+
+// A dimension value typed as a range, like `0..360` or `10..width`, sweeps the dimension
+// and traces the selected dots. Angles are swept counterclockwise from the first line and may
+// go past a half turn.
+pub fn start_simulation(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut input: ResMut<DimensionInput>,
+    mut simulation: ResMut<Simulation>,
+    parameters: Res<Parameters>,
+    dimensions: Query<&Dimension>,
+    traced: SelectedDots,
+) {
+    if !keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        return;
+    }
+    let Some((from, to)) = input.text.split_once("..") else {
+        return;
+    };
+    let Some((entity, dimension)) = input.dimension.and_then(|entity| {
+        dimensions
+            .get(entity)
+            .ok()
+            .map(|dimension| (entity, dimension))
+    }) else {
+        return;
+    };
+    let range = evaluate_dimension(&parameters, dimension.kind, from)
+        .and_then(|(from, _)| Ok((from, evaluate_dimension(&parameters, dimension.kind, to)?.0)));
+    let is_valid = |value: f32| match dimension.kind {
+        DimensionKind::Angle(..) => value.is_finite(),
+        kind => kind.is_valid(value),
+    };
+    let (from, to) = match range {
+        Ok((from, to)) if is_valid(from) && is_valid(to) => (from, to),
+        Ok(_) => {
+            warn!("Invalid simulation range: {:?}", input.text);
+            input.text.clear();
+            return;
+        }
+        Err(error) => {
+            warn!("Invalid simulation range {:?}: {}", input.text, error);
+            input.text.clear();
+            return;
+        }
+    };
+    *simulation = Simulation {
+        dimension: Some(entity),
+        from,
+        to,
+        initial: dimension.value,
+        expression: dimension.expression.clone(),
+        value: dimension.value,
+        traces: traced.iter().map(|dot| (dot, Vec::new())).collect(),
+        ..default()
+    };
+    *input = DimensionInput::default();
+    if simulation.traces.is_empty() {
+        info!("No dots selected, so nothing is traced");
+    }
+    info!(
+        "Simulating {} to {}, Esc stops",
+        dimension.kind.format(from),
+        dimension.kind.format(to)
+    );
+}
+
+// Step the dimension once per frame, stopping where the sketch can not follow
+pub fn drive_simulation(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut simulation: ResMut<Simulation>,
+    mut solver: ResMut<ConstraintSolver>,
+    mut dimensions: Query<&mut Dimension>,
+) {
+    let Some(entity) = simulation.dimension else {
+        return;
+    };
+    let Ok(mut dimension) = dimensions.get_mut(entity) else {
+        simulation.is_finished = true;
+        return;
+    };
+    // Conflicting steps are reverted by the diagnostics
+    if simulation.step > 0 && (dimension.value - simulation.value).abs() > f32::EPSILON {
+        warn!(
+            "The sketch can not reach {}",
+            dimension.kind.format(simulation.value)
+        );
+        simulation.is_finished = true;
+        return;
+    }
+    if simulation.step > SIMULATION_STEPS || keyboard.just_pressed(KeyCode::Escape) {
+        simulation.is_finished = true;
+        return;
+    }
+    let progress = simulation.step as f32 / SIMULATION_STEPS as f32;
+    let value = simulation.from + (simulation.to - simulation.from) * progress;
+    commands.entity(entity).insert(Pending::Edited(
+        dimension.value,
+        dimension.expression.clone(),
+    ));
+    // The sweep overrides any parameter the dimension followed until it finishes
+    dimension.expression = None;
+    if let DimensionKind::Angle(..) = dimension.kind {
+        dimension.is_signed = true;
+    }
+    dimension.value = value;
+    simulation.value = value;
+    simulation.step += 1;
+    solver.is_dirty = true;
+}
+
+pub fn record_simulation(mut simulation: ResMut<Simulation>, dots: DotTransforms) {
+    for (dot, path) in simulation.traces.iter_mut() {
+        let Ok(transform) = dots.get(*dot) else {
+            continue;
+        };
+        let position = transform.translation;
+        if path
+            .last()
+            .is_none_or(|last| last.distance(position) > MIN_TRACE_STEP)
+        {
+            path.push(position);
+        }
+    }
+}
+
+// The dimension goes back to following its parameters, or keeps the angle it was swept to
+pub fn restore_simulated_dimension(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    mut solver: ResMut<ConstraintSolver>,
+    geometry: DimensionGeometry,
+    mut dimensions: Query<&mut Dimension>,
+) {
+    let Some(entity) = simulation.dimension else {
+        return;
+    };
+    let Ok(mut dimension) = dimensions.get_mut(entity) else {
+        return;
+    };
+    if dimension.is_signed {
+        dimension.is_signed = false;
+        if let Some(angle) = geometry.measure(dimension.kind) {
+            dimension.value = angle;
+        }
+        solver.is_dirty = true;
+    }
+    if simulation.expression.is_some() {
+        commands
+            .entity(entity)
+            .insert(Pending::Edited(dimension.value, None));
+        dimension.value = simulation.initial;
+        dimension.expression = simulation.expression.clone();
+        solver.is_dirty = true;
+    }
+}
+
+// Each traced path is kept as a construction trace through every recorded position
+pub fn finish_simulation(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    ui_materials: Res<UIMaterials>,
+    mut simulation: ResMut<Simulation>,
+) {
+    for (_, path) in std::mem::take(&mut simulation.traces) {
+        if path.len() < 2 {
+            continue;
+        }
+        commands.spawn((
+            Construction,
+            Mesh3d(meshes.add(curve_mesh(&path))),
+            MeshMaterial3d(ui_materials.construction.clone()),
+            MESH_VISIBILITY,
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+            Trace(path),
+        ));
+    }
+    info!("Simulation finished");
+    *simulation = Simulation::default();
+}
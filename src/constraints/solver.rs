@@ -0,0 +1,974 @@
+use std::f64::consts::{PI, TAU};
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::system::SystemParam;
+use bevy::math::DVec2;
+use bevy::prelude::*;
+
+use crate::sketching::arc::Arc;
+use crate::sketching::circle::Circle;
+use crate::sketching::curve::DotTransforms;
+use crate::sketching::dot::Dot;
+use crate::sketching::line::Line;
+use crate::sketching::sketch::Moving;
+
+use super::constraint::Constraint;
+use super::diagnostic::{ConstraintDiagnostic, DiagnosticKind, Pending};
+use super::dimension::{Dimension, DimensionKind};
+
+// Largest remaining residual that still counts as satisfied
+pub const SOLVER_TOLERANCE: f64 = 1e-6;
+pub const MAX_SOLVER_ITERATIONS: usize = 50;
+
+const FINITE_DIFFERENCE_STEP: f64 = 1e-7;
+// Jacobian rows shorter than this after removing earlier rows are dependent on them
+const RANK_TOLERANCE: f64 = 1e-5;
+const MIN_DAMPING: f64 = 1e-12;
+const MAX_DAMPING: f64 = 1e8;
+// How much harder dragged dots are to move than the geometry following them
+const DRAGGED_WEIGHT: f64 = 1e4;
+
+#[derive(Resource, Debug, Default)]
+pub struct ConstraintSolver {
+    pub is_dirty: bool,
+}
+
+// A residual function over point indices that is zero when its constraint holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Equation {
+    Coincident(usize, usize),
+    Horizontal(usize, usize),
+    Vertical(usize, usize),
+    Parallel([usize; 4]),
+    Perpendicular([usize; 4]),
+    EqualLength([usize; 4]),
+    // Point, then the start and end of the line
+    PointOnLine(usize, usize, usize),
+    // Point, then the center and radius of the circle
+    PointOnCircle(usize, usize, f64),
+    // Point, then the start, end and sweep of the arc
+    PointOnArc(usize, usize, usize, f64),
+    // Point, then the start and end of the line
+    Midpoint(usize, usize, usize),
+    Distance(usize, usize, f64),
+    HorizontalDistance(usize, usize, f64),
+    VerticalDistance(usize, usize, f64),
+    // Unsigned angle between two lines in radians
+    Angle([usize; 4], f64),
+    // Counterclockwise angle from the first line to the second in radians, in any number of turns
+    SignedAngle([usize; 4], f64),
+}
+
+impl Equation {
+    // Point indices the equation depends on
+    pub fn points(&self) -> Vec<usize> {
+        match *self {
+            Equation::Coincident(a, b)
+            | Equation::Horizontal(a, b)
+            | Equation::Vertical(a, b)
+            | Equation::Distance(a, b, _)
+            | Equation::HorizontalDistance(a, b, _)
+            | Equation::VerticalDistance(a, b, _)
+            | Equation::PointOnCircle(a, b, _) => vec![a, b],
+            Equation::PointOnLine(point, a, b)
+            | Equation::PointOnArc(point, a, b, _)
+            | Equation::Midpoint(point, a, b) => vec![point, a, b],
+            Equation::Parallel(points)
+            | Equation::Perpendicular(points)
+            | Equation::EqualLength(points)
+            | Equation::Angle(points, _)
+            | Equation::SignedAngle(points, _) => points.to_vec(),
+        }
+    }
+
+    // Number of residuals the equation contributes
+    fn rows(&self) -> usize {
+        match self {
+            Equation::Coincident(..) | Equation::Midpoint(..) => 2,
+            _ => 1,
+        }
+    }
+
+    fn residuals(&self, points: &[DVec2], out: &mut Vec<f64>) {
+        match *self {
+            Equation::Coincident(a, b) => {
+                let offset = points[a] - points[b];
+                out.extend([offset.x, offset.y]);
+            }
+            Equation::Horizontal(a, b) => out.push(points[b].y - points[a].y),
+            Equation::Vertical(a, b) => out.push(points[b].x - points[a].x),
+            Equation::Parallel(lines) => {
+                let (first, second) = line_directions(points, lines);
+                out.push(first.perp_dot(second));
+            }
+            Equation::Perpendicular(lines) => {
+                let (first, second) = line_directions(points, lines);
+                out.push(first.dot(second));
+            }
+            Equation::EqualLength([a, b, c, d]) => {
+                out.push(points[a].distance(points[b]) - points[c].distance(points[d]));
+            }
+            Equation::PointOnLine(point, a, b) => {
+                let direction = (points[b] - points[a]).normalize_or_zero();
+                out.push(direction.perp_dot(points[point] - points[a]));
+            }
+            Equation::PointOnCircle(point, center, radius) => {
+                out.push(points[point].distance(points[center]) - radius);
+            }
+            Equation::PointOnArc(point, start, end, sweep) => {
+                match arc_center(points[start], points[end], sweep) {
+                    Some(center) => {
+                        out.push(points[point].distance(center) - points[start].distance(center))
+                    }
+                    // A flat arc is the straight segment between its ends
+                    None => {
+                        let direction = (points[end] - points[start]).normalize_or_zero();
+                        out.push(direction.perp_dot(points[point] - points[start]));
+                    }
+                }
+            }
+            Equation::Midpoint(point, a, b) => {
+                let offset = points[point] - (points[a] + points[b]) / 2.;
+                out.extend([offset.x, offset.y]);
+            }
+            Equation::Distance(a, b, distance) => {
+                out.push(points[a].distance(points[b]) - distance);
+            }
+            Equation::HorizontalDistance(a, b, distance) => {
+                out.push((points[b].x - points[a].x).abs() - distance);
+            }
+            Equation::VerticalDistance(a, b, distance) => {
+                out.push((points[b].y - points[a].y).abs() - distance);
+            }
+            Equation::Angle(lines, angle) => {
+                let (first, second) = line_directions(points, lines);
+                out.push(first.perp_dot(second).abs().atan2(first.dot(second)) - angle);
+            }
+            Equation::SignedAngle(lines, angle) => {
+                let (first, second) = line_directions(points, lines);
+                let difference = first.perp_dot(second).atan2(first.dot(second)) - angle;
+                out.push((difference + PI).rem_euclid(TAU) - PI);
+            }
+        }
+    }
+}
+
+// Unit directions of two lines given as start and end indices
+fn line_directions(points: &[DVec2], [a, b, c, d]: [usize; 4]) -> (DVec2, DVec2) {
+    (
+        (points[b] - points[a]).normalize_or_zero(),
+        (points[d] - points[c]).normalize_or_zero(),
+    )
+}
+
+// Center of the circle an arc from start to end with the given sweep lies on,
+// or None when the arc degenerates to a straight segment
+fn arc_center(start: DVec2, end: DVec2, sweep: f64) -> Option<DVec2> {
+    let chord = end - start;
+    let half_chord = chord.length() / 2.;
+    if half_chord < f64::EPSILON || sweep.abs() < 1e-4 {
+        return None;
+    }
+    Some((start + end) / 2. + chord.perp().normalize() * (half_chord / (sweep / 2.).tan()))
+}
+
+// Which points can still move and which equations say nothing new
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FreedomAnalysis {
+    pub is_free: Vec<bool>,
+    pub redundant: Vec<usize>,
+    // For each redundant equation, it and the earlier equations it depends on
+    pub circuits: Vec<Vec<usize>>,
+    pub degrees_of_freedom: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveReport {
+    pub is_converged: bool,
+    pub iterations: usize,
+    pub residual: f64,
+}
+
+// Points and the equations between them, with locked points held in place
+// and heavier points moved less than lighter ones
+#[derive(Debug, Default)]
+pub struct SketchSystem {
+    pub points: Vec<DVec2>,
+    pub locked: Vec<bool>,
+    pub weights: Vec<f64>,
+    pub equations: Vec<Equation>,
+}
+
+impl SketchSystem {
+    pub fn add_point(&mut self, position: DVec2) -> usize {
+        self.points.push(position);
+        self.locked.push(false);
+        self.weights.push(1.);
+        self.points.len() - 1
+    }
+
+    pub fn residuals(&self, points: &[DVec2]) -> Vec<f64> {
+        let mut residuals = Vec::with_capacity(self.equations.len() * 2);
+        for equation in &self.equations {
+            equation.residuals(points, &mut residuals);
+        }
+        residuals
+    }
+
+    // Coordinate indices the solver may change, two per unlocked point
+    fn free_coordinates(&self) -> Vec<usize> {
+        (0..self.points.len() * 2)
+            .filter(|coordinate| !self.locked[coordinate / 2])
+            .collect()
+    }
+
+    // Row-major Jacobian of the residuals over the free coordinates
+    fn jacobian(&self, residuals: &[f64], free: &[usize]) -> Vec<f64> {
+        let mut jacobian = vec![0.; residuals.len() * free.len()];
+        let mut points = self.points.clone();
+        for (column, coordinate) in free.iter().enumerate() {
+            let point = &mut points[coordinate / 2];
+            let value = point[coordinate % 2];
+            point[coordinate % 2] = value + FINITE_DIFFERENCE_STEP;
+            let shifted = self.residuals(&points);
+            points[coordinate / 2][coordinate % 2] = value;
+            for (row, (shifted, residual)) in shifted.iter().zip(residuals).enumerate() {
+                jacobian[row * free.len() + column] = (shifted - residual) / FINITE_DIFFERENCE_STEP;
+            }
+        }
+        jacobian
+    }
+
+    // Damped Gauss-Newton steps of minimal weighted norm, so under-constrained points move as little as possible
+    pub fn solve(&mut self) -> SolveReport {
+        let free = self.free_coordinates();
+        let mut residuals = self.residuals(&self.points);
+        let mut cost = sum_of_squares(&residuals);
+        let mut damping = 1e-6;
+        let mut iterations = 0;
+
+        while iterations < MAX_SOLVER_ITERATIONS && max_abs(&residuals) > SOLVER_TOLERANCE {
+            iterations += 1;
+            let rows = residuals.len();
+            let jacobian = self.jacobian(&residuals, &free);
+            let inverse_weights: Vec<f64> = free
+                .iter()
+                .map(|coordinate| 1. / self.weights[coordinate / 2])
+                .collect();
+
+            // (J * W^-1 * J^T + damping * I) * y = r, then step = -W^-1 * J^T * y
+            let mut normal = vec![0.; rows * rows];
+            for i in 0..rows {
+                for j in 0..rows {
+                    normal[i * rows + j] = (0..free.len())
+                        .map(|k| {
+                            jacobian[i * free.len() + k]
+                                * jacobian[j * free.len() + k]
+                                * inverse_weights[k]
+                        })
+                        .sum();
+                }
+                normal[i * rows + i] += damping;
+            }
+            let Some(y) = solve_linear(normal, residuals.clone(), rows) else {
+                damping *= 10.;
+                continue;
+            };
+
+            let mut candidate = self.points.clone();
+            for (column, coordinate) in free.iter().enumerate() {
+                let step: f64 = (0..rows)
+                    .map(|row| jacobian[row * free.len() + column] * y[row])
+                    .sum::<f64>()
+                    * inverse_weights[column];
+                candidate[coordinate / 2][coordinate % 2] -= step;
+            }
+            let candidate_residuals = self.residuals(&candidate);
+            let candidate_cost = sum_of_squares(&candidate_residuals);
+
+            if candidate_cost < cost {
+                self.points = candidate;
+                residuals = candidate_residuals;
+                cost = candidate_cost;
+                damping = (damping / 10.).max(MIN_DAMPING);
+            } else {
+                damping *= 10.;
+                if damping > MAX_DAMPING {
+                    break;
+                }
+            }
+        }
+
+        let residual = max_abs(&residuals);
+        SolveReport {
+            is_converged: residual <= SOLVER_TOLERANCE,
+            iterations,
+            residual,
+        }
+    }
+
+    // Rank of the Jacobian at the current points. Rows that depend on earlier ones
+    // mark their equation redundant, and a coordinate is only determined when it
+    // lies in the span of the independent rows.
+    pub fn analyze(&self) -> FreedomAnalysis {
+        let free = self.free_coordinates();
+        let residuals = self.residuals(&self.points);
+        let jacobian = self.jacobian(&residuals, &free);
+        let row_equations: Vec<usize> = self
+            .equations
+            .iter()
+            .enumerate()
+            .flat_map(|(index, equation)| std::iter::repeat_n(index, equation.rows()))
+            .collect();
+
+        // Orthonormal rows along with how each is made of the original rows
+        let mut basis: Vec<(Vec<f64>, Vec<f64>)> = Vec::new();
+        let mut redundant = Vec::new();
+        let mut circuits: Vec<Vec<usize>> = Vec::new();
+        for (row, equation) in row_equations.iter().enumerate() {
+            let mut vector = jacobian[row * free.len()..(row + 1) * free.len()].to_vec();
+            let mut combination = vec![0.; row_equations.len()];
+            combination[row] = 1.;
+            let scale = norm(&vector);
+            for (independent, independent_combination) in &basis {
+                let projection: f64 = vector.iter().zip(independent).map(|(a, b)| a * b).sum();
+                for (value, direction) in vector.iter_mut().zip(independent) {
+                    *value -= projection * direction;
+                }
+                for (value, part) in combination.iter_mut().zip(independent_combination) {
+                    *value -= projection * part;
+                }
+            }
+            let length = norm(&vector);
+            if length > RANK_TOLERANCE * scale.max(1.) {
+                basis.push((
+                    vector.iter().map(|value| value / length).collect(),
+                    combination.iter().map(|value| value / length).collect(),
+                ));
+                continue;
+            }
+
+            // The rows this one is a combination of form the smallest dependent set
+            let mut circuit: Vec<usize> = combination
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| value.abs() > RANK_TOLERANCE)
+                .map(|(other, _)| row_equations[other])
+                .collect();
+            circuit.dedup();
+            if redundant.last() == Some(equation) {
+                let last = circuits.len() - 1;
+                circuits[last].extend(circuit);
+                circuits[last].sort();
+                circuits[last].dedup();
+            } else {
+                redundant.push(*equation);
+                circuits.push(circuit);
+            }
+        }
+
+        let mut is_free = vec![false; self.points.len()];
+        for (column, coordinate) in free.iter().enumerate() {
+            let spanned: f64 = basis.iter().map(|(vector, _)| vector[column].powi(2)).sum();
+            if 1. - spanned > RANK_TOLERANCE {
+                is_free[coordinate / 2] = true;
+            }
+        }
+        FreedomAnalysis {
+            is_free,
+            redundant,
+            circuits,
+            degrees_of_freedom: free.len() - basis.len(),
+        }
+    }
+}
+
+fn norm(values: &[f64]) -> f64 {
+    sum_of_squares(values).sqrt()
+}
+
+fn sum_of_squares(values: &[f64]) -> f64 {
+    values.iter().map(|value| value * value).sum()
+}
+
+// Infinite when any value is not finite, so a broken system never counts as solved
+fn max_abs(values: &[f64]) -> f64 {
+    values.iter().fold(0., |max, value| {
+        if value.is_finite() {
+            value.abs().max(max)
+        } else {
+            f64::INFINITY
+        }
+    })
+}
+
+// Gaussian elimination with partial pivoting on a row-major square matrix
+pub fn solve_linear(mut matrix: Vec<f64>, mut rhs: Vec<f64>, size: usize) -> Option<Vec<f64>> {
+    for column in 0..size {
+        let pivot = (column..size).max_by(|a, b| {
+            matrix[a * size + column]
+                .abs()
+                .total_cmp(&matrix[b * size + column].abs())
+        })?;
+        if matrix[pivot * size + column].abs() < f64::EPSILON {
+            return None;
+        }
+        if pivot != column {
+            for k in 0..size {
+                matrix.swap(pivot * size + k, column * size + k);
+            }
+            rhs.swap(pivot, column);
+        }
+        for row in column + 1..size {
+            let factor = matrix[row * size + column] / matrix[column * size + column];
+            for k in column..size {
+                matrix[row * size + k] -= factor * matrix[column * size + k];
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+    let mut solution = vec![0.; size];
+    for row in (0..size).rev() {
+        let known: f64 = (row + 1..size)
+            .map(|k| matrix[row * size + k] * solution[k])
+            .sum();
+        solution[row] = (rhs[row] - known) / matrix[row * size + row];
+    }
+    Some(solution)
+}
+
+// Constraint and dimension entities along with the geometry they refer to
+#[derive(SystemParam)]
+pub struct SketchConstraints<'w, 's> {
+    constraints: Query<'w, 's, (Entity, &'static Constraint, Has<Pending>)>,
+    dimensions: Query<'w, 's, (Entity, &'static Dimension, Has<Pending>)>,
+    lines: Query<'w, 's, &'static Line>,
+    circles: Query<'w, 's, &'static Circle>,
+    arcs: Query<'w, 's, &'static Arc>,
+}
+
+impl SketchConstraints<'_, '_> {
+    // Pending constraints go last, so the analysis blames them rather than older ones
+    pub fn build(&self, dots: &DotTransforms) -> ConstrainedSketch {
+        let mut sketch = ConstrainedSketch::default();
+        for is_pending in [false, true] {
+            for (entity, constraint, _) in self
+                .constraints
+                .iter()
+                .filter(|(_, _, pending)| *pending == is_pending)
+            {
+                sketch.add_constraint(
+                    entity,
+                    constraint,
+                    &self.lines,
+                    (&self.circles, &self.arcs),
+                    dots,
+                );
+            }
+            for (entity, dimension, _) in self
+                .dimensions
+                .iter()
+                .filter(|(_, _, pending)| *pending == is_pending)
+            {
+                sketch.add_dimension(entity, dimension, &self.lines, &self.arcs, dots);
+            }
+        }
+        sketch.pending = self
+            .constraints
+            .iter()
+            .map(|(entity, _, is_pending)| (entity, is_pending))
+            .chain(
+                self.dimensions
+                    .iter()
+                    .map(|(entity, _, is_pending)| (entity, is_pending)),
+            )
+            .filter(|(_, is_pending)| *is_pending)
+            .map(|(entity, _)| entity)
+            .collect();
+        sketch
+    }
+
+    // Geometry a constraint or dimension refers to
+    pub fn referenced(&self, entity: Entity) -> Vec<Entity> {
+        if let Ok((_, constraint, _)) = self.constraints.get(entity) {
+            constraint.entities()
+        } else if let Ok((_, dimension, _)) = self.dimensions.get(entity) {
+            dimension.kind.entities()
+        } else {
+            Vec::new()
+        }
+    }
+
+    // Dependent sets that involve pending constraints
+    pub fn diagnose(
+        &self,
+        sketch: &ConstrainedSketch,
+        analysis: &FreedomAnalysis,
+        kind: DiagnosticKind,
+    ) -> Vec<ConstraintDiagnostic> {
+        let mut diagnostics: Vec<ConstraintDiagnostic> = analysis
+            .circuits
+            .iter()
+            .map(|circuit| sketch.circuit_sources(circuit))
+            .filter(|sources| sources.iter().any(|source| sketch.pending.contains(source)))
+            .map(|constraints| self.diagnostic(kind, constraints, sketch))
+            .collect();
+        // Conflicts through fixed dots alone leave no dependent rows to point at
+        if diagnostics.is_empty() && kind == DiagnosticKind::Conflicting {
+            diagnostics.push(self.diagnostic(kind, sketch.pending.clone(), sketch));
+        }
+        diagnostics
+    }
+
+    fn diagnostic(
+        &self,
+        kind: DiagnosticKind,
+        constraints: Vec<Entity>,
+        sketch: &ConstrainedSketch,
+    ) -> ConstraintDiagnostic {
+        let mut geometry: Vec<Entity> = constraints
+            .iter()
+            .flat_map(|constraint| self.referenced(*constraint))
+            .collect();
+        geometry.sort();
+        geometry.dedup();
+        // Nothing pending is kept once the sketch conflicts
+        let rejected = match kind {
+            DiagnosticKind::Conflicting => sketch.pending.clone(),
+            DiagnosticKind::Redundant => Vec::new(),
+        };
+        ConstraintDiagnostic {
+            kind,
+            constraints,
+            geometry,
+            rejected,
+        }
+    }
+}
+
+// Solver input built from the constraint entities, remembering which dot each point is
+#[derive(Debug, Default)]
+pub struct ConstrainedSketch {
+    pub system: SketchSystem,
+    pub dots: Vec<Entity>,
+    // Constraint or dimension behind each equation
+    pub sources: Vec<Entity>,
+    // Fixed constraints by the point they lock
+    pub fixed: Vec<(usize, Entity)>,
+    pub pending: Vec<Entity>,
+    indices: EntityHashMap<usize>,
+}
+
+impl ConstrainedSketch {
+    pub fn point(&mut self, dot: Entity, dots: &DotTransforms) -> Option<usize> {
+        if let Some(index) = self.indices.get(&dot) {
+            return Some(*index);
+        }
+        let position = dots.get(dot).ok()?.translation;
+        let index = self.system.add_point(position.truncate().as_dvec2());
+        self.dots.push(dot);
+        self.indices.insert(dot, index);
+        Some(index)
+    }
+
+    pub fn line(
+        &mut self,
+        line: Entity,
+        lines: &Query<&Line>,
+        dots: &DotTransforms,
+    ) -> Option<(usize, usize)> {
+        let line = lines.get(line).ok()?;
+        Some((self.point(line.start, dots)?, self.point(line.end, dots)?))
+    }
+
+    pub fn lines(
+        &mut self,
+        first: Entity,
+        second: Entity,
+        lines: &Query<&Line>,
+        dots: &DotTransforms,
+    ) -> Option<[usize; 4]> {
+        let (a, b) = self.line(first, lines, dots)?;
+        let (c, d) = self.line(second, lines, dots)?;
+        Some([a, b, c, d])
+    }
+
+    // Constraints referring to missing entities are skipped
+    pub fn add_constraint(
+        &mut self,
+        entity: Entity,
+        constraint: &Constraint,
+        lines: &Query<&Line>,
+        curves: (&Query<&Circle>, &Query<&Arc>),
+        dots: &DotTransforms,
+    ) {
+        let equation = match *constraint {
+            Constraint::Coincident(a, b) => self
+                .point(a, dots)
+                .zip(self.point(b, dots))
+                .map(|(a, b)| Equation::Coincident(a, b)),
+            Constraint::Horizontal(line) => self
+                .line(line, lines, dots)
+                .map(|(a, b)| Equation::Horizontal(a, b)),
+            Constraint::Vertical(line) => self
+                .line(line, lines, dots)
+                .map(|(a, b)| Equation::Vertical(a, b)),
+            Constraint::Parallel(first, second) => self
+                .lines(first, second, lines, dots)
+                .map(Equation::Parallel),
+            Constraint::Perpendicular(first, second) => self
+                .lines(first, second, lines, dots)
+                .map(Equation::Perpendicular),
+            Constraint::EqualLength(first, second) => self
+                .lines(first, second, lines, dots)
+                .map(Equation::EqualLength),
+            Constraint::PointOnCurve(dot, curve) => {
+                self.point_on_curve(dot, curve, lines, curves, dots)
+            }
+            Constraint::Midpoint(dot, line) => self
+                .point(dot, dots)
+                .zip(self.line(line, lines, dots))
+                .map(|(point, (a, b))| Equation::Midpoint(point, a, b)),
+            Constraint::Fixed(dot, position) => {
+                if let Some(index) = self.point(dot, dots) {
+                    self.system.points[index] = position.truncate().as_dvec2();
+                    self.system.locked[index] = true;
+                    self.fixed.push((index, entity));
+                }
+                None
+            }
+        };
+        if let Some(equation) = equation {
+            self.system.equations.push(equation);
+            self.sources.push(entity);
+        }
+    }
+
+    fn point_on_curve(
+        &mut self,
+        dot: Entity,
+        curve: Entity,
+        lines: &Query<&Line>,
+        (circles, arcs): (&Query<&Circle>, &Query<&Arc>),
+        dots: &DotTransforms,
+    ) -> Option<Equation> {
+        let point = self.point(dot, dots)?;
+        if let Some((a, b)) = self.line(curve, lines, dots) {
+            Some(Equation::PointOnLine(point, a, b))
+        } else if let Ok(circle) = circles.get(curve) {
+            let center = self.point(circle.center, dots)?;
+            Some(Equation::PointOnCircle(point, center, circle.radius as f64))
+        } else {
+            let arc = arcs.get(curve).ok()?;
+            let (start, end) = (self.point(arc.start, dots)?, self.point(arc.end, dots)?);
+            Some(Equation::PointOnArc(point, start, end, arc.sweep as f64))
+        }
+    }
+
+    // Circle radii are driven directly, so only arcs reach the solver among curves
+    pub fn add_dimension(
+        &mut self,
+        entity: Entity,
+        dimension: &Dimension,
+        lines: &Query<&Line>,
+        arcs: &Query<&Arc>,
+        dots: &DotTransforms,
+    ) {
+        let value = dimension.value as f64;
+        let equation = match dimension.kind {
+            DimensionKind::Distance(a, b) => self
+                .point(a, dots)
+                .zip(self.point(b, dots))
+                .map(|(a, b)| Equation::Distance(a, b, value)),
+            DimensionKind::LineLength(line) => self
+                .line(line, lines, dots)
+                .map(|(a, b)| Equation::Distance(a, b, value)),
+            DimensionKind::HorizontalDistance(a, b) => self
+                .point(a, dots)
+                .zip(self.point(b, dots))
+                .map(|(a, b)| Equation::HorizontalDistance(a, b, value)),
+            DimensionKind::VerticalDistance(a, b) => self
+                .point(a, dots)
+                .zip(self.point(b, dots))
+                .map(|(a, b)| Equation::VerticalDistance(a, b, value)),
+            DimensionKind::Angle(first, second) => {
+                self.lines(first, second, lines, dots).map(|lines| {
+                    if dimension.is_signed {
+                        Equation::SignedAngle(lines, value.to_radians())
+                    } else {
+                        Equation::Angle(lines, value.to_radians())
+                    }
+                })
+            }
+            DimensionKind::Radius(curve) | DimensionKind::Diameter(curve) => {
+                let Ok(arc) = arcs.get(curve) else {
+                    return;
+                };
+                let radius = if matches!(dimension.kind, DimensionKind::Diameter(_)) {
+                    value / 2.
+                } else {
+                    value
+                };
+                // The chord of an arc with a fixed sweep grows with its radius
+                let chord = 2. * radius * (arc.sweep.abs() as f64 / 2.).sin();
+                self.point(arc.start, dots)
+                    .zip(self.point(arc.end, dots))
+                    .map(|(a, b)| Equation::Distance(a, b, chord))
+            }
+        };
+        if let Some(equation) = equation {
+            self.system.equations.push(equation);
+            self.sources.push(entity);
+        }
+    }
+
+    // Constraints behind a set of equations, including those fixing their points
+    pub fn circuit_sources(&self, circuit: &[usize]) -> Vec<Entity> {
+        let points: Vec<usize> = circuit
+            .iter()
+            .flat_map(|equation| self.system.equations[*equation].points())
+            .collect();
+        let mut sources: Vec<Entity> = circuit
+            .iter()
+            .map(|equation| self.sources[*equation])
+            .chain(
+                self.fixed
+                    .iter()
+                    .filter(|(point, _)| points.contains(point))
+                    .map(|(_, fixed)| *fixed),
+            )
+            .collect();
+        sources.dedup();
+        sources
+    }
+
+    pub fn index(&self, dot: Entity) -> Option<usize> {
+        self.indices.get(&dot).copied()
+    }
+
+    // Dots no constraint refers to still belong to the sketch, free to move
+    pub fn add_dots(&mut self, entities: impl IntoIterator<Item = Entity>, dots: &DotTransforms) {
+        for dot in entities {
+            self.point(dot, dots);
+        }
+    }
+
+    // Dots being dragged act as targets the rest of the sketch follows
+    pub fn weigh_dragged(&mut self, moving: &Query<(), (With<Dot>, With<Moving>)>) {
+        for (index, dot) in self.dots.iter().enumerate() {
+            if moving.contains(*dot) {
+                self.system.weights[index] = DRAGGED_WEIGHT;
+            }
+        }
+    }
+
+    // Write solved points back, leaving dots that barely moved untouched
+    pub fn apply(&self, dots: &mut Query<&mut Transform, With<Dot>>) {
+        for (dot, point) in self.dots.iter().zip(&self.system.points) {
+            let Ok(mut transform) = dots.get_mut(*dot) else {
+                continue;
+            };
+            let position = point.as_vec2().extend(transform.translation.z);
+            if transform.translation.distance(position) > SOLVER_TOLERANCE as f32 {
+                transform.translation = position;
+            }
+        }
+    }
+}
+
+pub fn needs_solve(solver: Res<ConstraintSolver>) -> bool {
+    solver.is_dirty
+}
+
+pub fn mark_solver_dirty(mut solver: ResMut<ConstraintSolver>) {
+    solver.is_dirty = true;
+}
+
+pub fn solve_constraints(
+    mut solver: ResMut<ConstraintSolver>,
+    constraints: SketchConstraints,
+    mut dots: ParamSet<(DotTransforms, Query<&mut Transform, With<Dot>>)>,
+    moving: Query<(), (With<Dot>, With<Moving>)>,
+    mut diagnostics: EventWriter<ConstraintDiagnostic>,
+) {
+    solver.is_dirty = false;
+    let mut sketch = constraints.build(&dots.p0());
+    if sketch.dots.is_empty() {
+        return;
+    }
+    sketch.weigh_dragged(&moving);
+    let unsolved = sketch.system.points.clone();
+    let report = sketch.system.solve();
+
+    // New constraints are checked before the result is applied, so a conflict leaves the sketch as it was
+    if !sketch.pending.is_empty() {
+        if !report.is_converged {
+            sketch.system.points = unsolved;
+            let analysis = sketch.system.analyze();
+            diagnostics.write_batch(constraints.diagnose(
+                &sketch,
+                &analysis,
+                DiagnosticKind::Conflicting,
+            ));
+            return;
+        }
+        let analysis = sketch.system.analyze();
+        diagnostics.write_batch(constraints.diagnose(
+            &sketch,
+            &analysis,
+            DiagnosticKind::Redundant,
+        ));
+    } else if !report.is_converged && moving.is_empty() {
+        // Drags through unreachable positions are expected, so only settled sketches warn
+        warn!(
+            "Constraints not satisfied after {} iterations, residual {:e}",
+            report.iterations, report.residual
+        );
+    }
+    sketch.apply(&mut dots.p1());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(points: &[(f64, f64)], equations: Vec<Equation>) -> SketchSystem {
+        let mut system = SketchSystem::default();
+        for (x, y) in points {
+            system.add_point(DVec2::new(*x, *y));
+        }
+        system.equations = equations;
+        system
+    }
+
+    #[test]
+    fn horizontal_line_converges() {
+        let mut system = system(&[(0., 0.), (3., 1.)], vec![Equation::Horizontal(0, 1)]);
+        let report = system.solve();
+        assert!(report.is_converged);
+        assert!((system.points[0].y - system.points[1].y).abs() < 1e-6);
+        // The line stays about as long as it was
+        assert!((system.points[0].distance(system.points[1]) - 3.).abs() < 0.5);
+    }
+
+    #[test]
+    fn perpendicular_lines_converge() {
+        let mut system = system(
+            &[(0., 0.), (4., 0.), (1., 0.), (3., 2.)],
+            vec![Equation::Perpendicular([0, 1, 2, 3])],
+        );
+        system.locked[0] = true;
+        system.locked[1] = true;
+        let report = system.solve();
+        assert!(report.is_converged);
+        let first = system.points[1] - system.points[0];
+        let second = system.points[3] - system.points[2];
+        assert!(first.normalize().dot(second.normalize()).abs() < 1e-6);
+        assert_eq!(system.points[0], DVec2::ZERO);
+    }
+
+    #[test]
+    fn signed_angle_turns_past_half_turn() {
+        let mut system = system(
+            &[(0., 0.), (1., 0.), (0., 0.), (1., 0.1)],
+            vec![Equation::SignedAngle([0, 1, 2, 3], 270_f64.to_radians())],
+        );
+        system.locked[0] = true;
+        system.locked[1] = true;
+        system.locked[2] = true;
+        assert!(system.solve().is_converged);
+        let second = system.points[3] - system.points[2];
+        // A quarter turn clockwise is three quarters counterclockwise
+        assert!((second.normalize() - DVec2::NEG_Y).length() < 1e-6);
+    }
+
+    #[test]
+    fn flat_arcs_stay_finite() {
+        // Point on an arc with no sweep, which is the segment between its ends
+        let mut system = system(
+            &[(0., 1.), (-2., 0.), (2., 0.)],
+            vec![Equation::PointOnArc(0, 1, 2, 0.)],
+        );
+        system.locked[1] = true;
+        system.locked[2] = true;
+        let report = system.solve();
+        assert!(report.is_converged);
+        assert!(system.points.iter().all(|point| point.is_finite()));
+        assert!(system.points[0].y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn locked_points_do_not_move() {
+        let mut system = system(
+            &[(0., 0.), (1., 1.)],
+            vec![Equation::Distance(0, 1, 2.), Equation::Vertical(0, 1)],
+        );
+        system.locked[0] = true;
+        assert!(system.solve().is_converged);
+        assert_eq!(system.points[0], DVec2::ZERO);
+        assert!((system.points[1].x).abs() < 1e-6);
+        assert!((system.points[1].y.abs() - 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn degrees_of_freedom() {
+        let system = system(&[(0., 0.), (3., 0.)], vec![Equation::Horizontal(0, 1)]);
+        let analysis = system.analyze();
+        assert_eq!(analysis.degrees_of_freedom, 3);
+        assert!(analysis.redundant.is_empty());
+        assert_eq!(analysis.is_free, vec![true, true]);
+
+        let mut fixed = system;
+        fixed.locked[0] = true;
+        fixed.equations.push(Equation::Distance(0, 1, 3.));
+        let analysis = fixed.analyze();
+        assert_eq!(analysis.degrees_of_freedom, 0);
+        assert_eq!(analysis.is_free, vec![false, false]);
+    }
+
+    #[test]
+    fn over_constrained_is_reported() {
+        // Consistent but repeated
+        let repeated = system(
+            &[(0., 0.), (3., 0.)],
+            vec![Equation::Horizontal(0, 1), Equation::Horizontal(0, 1)],
+        );
+        let analysis = repeated.analyze();
+        assert_eq!(analysis.redundant, vec![1]);
+        assert_eq!(analysis.circuits, vec![vec![0, 1]]);
+
+        // Conflicting distances can not both hold
+        let mut conflicting = system(
+            &[(0., 0.), (3., 0.), (0., 5.)],
+            vec![
+                Equation::Distance(0, 1, 1.),
+                Equation::Horizontal(0, 2),
+                Equation::Distance(0, 1, 2.),
+            ],
+        );
+        conflicting.locked[0] = true;
+        assert!(!conflicting.solve().is_converged);
+        let analysis = conflicting.analyze();
+        assert_eq!(analysis.redundant, vec![2]);
+        assert_eq!(analysis.circuits, vec![vec![0, 2]]);
+    }
+
+    #[test]
+    fn non_finite_residuals_are_never_solved() {
+        assert_eq!(max_abs(&[1., f64::NAN, -2.]), f64::INFINITY);
+        assert_eq!(max_abs(&[1., -2.]), 2.);
+    }
+
+    #[test]
+    fn solve_linear_solves_regular_systems() {
+        let solution = solve_linear(vec![0., 2., 1., 1.], vec![4., 3.], 2).unwrap();
+        assert!((solution[0] - 1.).abs() < 1e-12);
+        assert!((solution[1] - 2.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn solve_linear_rejects_singular_systems() {
+        assert_eq!(solve_linear(vec![1., 2., 2., 4.], vec![1., 2.], 2), None);
+        assert_eq!(solve_linear(vec![0.; 9], vec![0.; 3], 3), None);
+    }
+}
@@ -1,10 +1,29 @@
+use bevy::ecs::system::SystemParam;
 use bevy::input::common_conditions::input_just_pressed;
 use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
 
 use crate::assets::materials::ChangingMaterial;
+use crate::constraints::constraint::{Constraint, is_alt_pressed};
+use crate::constraints::dimension::DimensionLabel;
+use crate::constraints::simulation::Trace;
+use crate::grid::Grid;
 use crate::schedule::ScheduleSet;
+use crate::sketching::arc::Arc;
+use crate::sketching::circle::Circle;
+use crate::sketching::curve::{DotTransforms, SketchCurve};
+use crate::sketching::dot::Dot;
+use crate::sketching::ellipse::{Ellipse, EllipticalArc};
+use crate::sketching::line::Line;
 use crate::sketching::selection::{deselect_other_entities, select_entity};
-use crate::sketching::sketch::SketchMode;
+use crate::sketching::sketch::{Current, SketchMode};
+use crate::sketching::spline::Spline;
+
+// How far from the mouse on screen something can be and still be hovered
+const PICK_PIXELS: f32 = 6.;
+const MIN_PICK_PIXELS: f32 = 2.;
+const MAX_PICK_PIXELS: f32 = 20.;
+const PICK_PIXELS_STEP: f32 = 2.;
 
 #[derive(Resource, Default)]
 pub struct Cursor {
@@ -14,21 +33,166 @@ pub struct Cursor {
 
 #[derive(Resource)]
 pub struct Picking {
-    pub ray: Ray3d,
     pub hovered: Entity,
     pub prev_hovered: Entity,
+    // In screen pixels, so hovering does not depend on zoom. Alt+[ and Alt+] change it.
+    pub tolerance: f32,
 }
 
 impl Default for Picking {
     fn default() -> Self {
         Picking {
-            ray: Ray3d::new(Vec3::Z, Dir3::Z),
             hovered: Entity::PLACEHOLDER,
             prev_hovered: Entity::PLACEHOLDER,
+            tolerance: PICK_PIXELS,
         }
     }
 }
 
+// Dots are hovered before lines and curves, and those before regions like constraint glyphs
+// and dimension labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PickPriority {
+    Dot,
+    Curve,
+    Region,
+}
+
+#[derive(SystemParam)]
+pub struct PickableGeometry<'w, 's> {
+    dots: Query<'w, 's, (Entity, &'static Transform), With<Dot>>,
+    dot_transforms: DotTransforms<'w, 's>,
+    lines: Query<'w, 's, (Entity, &'static Line)>,
+    circles: Query<'w, 's, (Entity, &'static Circle)>,
+    arcs: Query<'w, 's, (Entity, &'static Arc)>,
+    splines: Query<'w, 's, (Entity, &'static Spline)>,
+    ellipses: Query<'w, 's, (Entity, &'static Ellipse)>,
+    elliptical_arcs: Query<'w, 's, (Entity, &'static EllipticalArc)>,
+    traces: Query<'w, 's, (Entity, &'static Trace)>,
+    regions: Query<'w, 's, (Entity, &'static GlobalTransform, &'static Aabb), With<Constraint>>,
+    labels: Query<
+        'w,
+        's,
+        (
+            &'static DimensionLabel,
+            &'static Node,
+            &'static ComputedNode,
+        ),
+    >,
+}
+
+impl PickableGeometry<'_, '_> {
+    // Screen distance from the mouse to everything but the geometry being sketched,
+    // which follows the mouse. Dots without a mesh count too.
+    fn picks(
+        &self,
+        current: &Current,
+        mouse: Vec2,
+        to_screen: impl Fn(Vec3) -> Option<Vec2>,
+    ) -> Vec<(PickPriority, f32, Entity)> {
+        let mut picks = Vec::new();
+        for (entity, transform) in self.dots.iter() {
+            if let Some(screen) = to_screen(transform.translation)
+                && !current.dots.contains(&entity)
+            {
+                picks.push((PickPriority::Dot, screen.distance(mouse), entity));
+            }
+        }
+
+        let mut polylines: Vec<(Entity, Vec<Vec3>)> = self
+            .lines
+            .iter()
+            .filter_map(|(entity, line)| {
+                let start = self.dot_transforms.get(line.start).ok()?.translation;
+                let end = self.dot_transforms.get(line.end).ok()?.translation;
+                Some((entity, vec![start, end]))
+            })
+            .collect();
+        polylines.extend(curve_polylines(&self.circles, &self.dot_transforms));
+        polylines.extend(curve_polylines(&self.arcs, &self.dot_transforms));
+        polylines.extend(curve_polylines(&self.splines, &self.dot_transforms));
+        polylines.extend(curve_polylines(&self.ellipses, &self.dot_transforms));
+        polylines.extend(curve_polylines(&self.elliptical_arcs, &self.dot_transforms));
+        polylines.extend(
+            self.traces
+                .iter()
+                .map(|(entity, trace)| (entity, trace.0.clone())),
+        );
+        for (entity, points) in polylines {
+            if current.lines.contains(&entity) || current.curves.contains(&entity) {
+                continue;
+            }
+            let screen: Vec<Vec2> = points.into_iter().filter_map(&to_screen).collect();
+            let distance = screen
+                .windows(2)
+                .map(|segment| distance_to_segment(mouse, segment[0], segment[1]))
+                .fold(f32::INFINITY, f32::min);
+            picks.push((PickPriority::Curve, distance, entity));
+        }
+
+        for (entity, transform, aabb) in self.regions.iter() {
+            let corners = [-1., 1.].map(|sign| {
+                let corner = Vec3::from(aabb.center) + Vec3::from(aabb.half_extents) * sign;
+                to_screen(transform.transform_point(corner))
+            });
+            let [Some(first), Some(second)] = corners else {
+                continue;
+            };
+            let rect = Rect::from_corners(first, second);
+            picks.push((PickPriority::Region, distance_to_rect(mouse, rect), entity));
+        }
+
+        // Dimension labels are screen space text, placed by their top left corner
+        for (label, node, computed) in self.labels.iter() {
+            let (Val::Px(left), Val::Px(top)) = (node.left, node.top) else {
+                continue;
+            };
+            let corner = Vec2::new(left, top);
+            let size = computed.size() * computed.inverse_scale_factor();
+            let rect = Rect::from_corners(corner, corner + size);
+            picks.push((
+                PickPriority::Region,
+                distance_to_rect(mouse, rect),
+                label.dimension,
+            ));
+        }
+        picks
+    }
+}
+
+fn curve_polylines<T: SketchCurve>(
+    curves: &Query<(Entity, &T)>,
+    dots: &DotTransforms,
+) -> Vec<(Entity, Vec<Vec3>)> {
+    curves
+        .iter()
+        .filter_map(|(entity, curve)| Some((entity, curve.points(dots)?)))
+        .collect()
+}
+
+// Closest pick within the tolerance, by priority first
+fn closest_pick(picks: Vec<(PickPriority, f32, Entity)>, tolerance: f32) -> Option<Entity> {
+    picks
+        .into_iter()
+        .filter(|(_, distance, _)| *distance <= tolerance)
+        .min_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map(|(_, _, entity)| entity)
+}
+
+fn distance_to_rect(point: Vec2, rect: Rect) -> f32 {
+    (rect.min - point)
+        .max(point - rect.max)
+        .max(Vec2::ZERO)
+        .length()
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let direction = end - start;
+    let t = ((point - start).dot(direction) / direction.length_squared().max(f32::EPSILON))
+        .clamp(0., 1.);
+    point.distance(start + direction * t)
+}
+
 pub struct CursorPlugin;
 
 impl Plugin for CursorPlugin {
@@ -38,6 +202,12 @@ impl Plugin for CursorPlugin {
             .add_systems(
                 Update,
                 (
+                    handle_pick_tolerance_keys.run_if(
+                        is_alt_pressed.and(
+                            input_just_pressed(KeyCode::BracketLeft)
+                                .or(input_just_pressed(KeyCode::BracketRight)),
+                        ),
+                    ),
                     update_cursor,
                     hover_entity,
                     mark_hovered_changing_material,
@@ -52,6 +222,19 @@ impl Plugin for CursorPlugin {
     }
 }
 
+// Alt+[ and Alt+] shrink or grow how far from the mouse things are hovered
+pub fn handle_pick_tolerance_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut picking: ResMut<Picking>,
+) {
+    picking.tolerance = if keyboard.just_pressed(KeyCode::BracketRight) {
+        (picking.tolerance + PICK_PIXELS_STEP).min(MAX_PICK_PIXELS)
+    } else {
+        (picking.tolerance - PICK_PIXELS_STEP).max(MIN_PICK_PIXELS)
+    };
+    info!("Pick tolerance: {} px", picking.tolerance);
+}
+
 pub fn is_cursor_moving(cursor: Res<Cursor>) -> bool {
     cursor.position - cursor.prev_position != Vec3::ZERO
 }
@@ -60,7 +243,7 @@ fn update_cursor(
     camera_query: Single<(&Camera, &GlobalTransform)>,
     windows: Query<&Window>,
     mut cursor: ResMut<Cursor>,
-    mut picking: ResMut<Picking>,
+    grid: Res<Grid>,
 ) {
     cursor.prev_position = cursor.position;
 
@@ -84,22 +267,40 @@ fn update_cursor(
         return;
     };
 
-    picking.ray = ray;
-    cursor.position = ray.get_point(distance);
+    let position = ray.get_point(distance);
+    // Hovering still follows the mouse, only the position tools use is snapped
+    cursor.position = if grid.is_snapping {
+        grid.snap(position)
+    } else {
+        position
+    };
 }
 
-pub fn hover_entity(mut ray_cast: MeshRayCast, mut picking: ResMut<Picking>) {
-    // Cast the ray and get the first hit
-    let Some((entity, _)) = ray_cast
-        .cast_ray(picking.ray, &MeshRayCastSettings::default())
-        .first()
+// Hovers whatever is closest to the mouse on screen within the tolerance
+pub fn hover_entity(
+    mut picking: ResMut<Picking>,
+    current: Res<Current>,
+    geometry: PickableGeometry,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window>,
+) {
+    picking.prev_hovered = picking.hovered;
+    picking.hovered = Entity::PLACEHOLDER;
+    let Some(mouse) = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position())
     else {
-        picking.prev_hovered = picking.hovered;
-        picking.hovered = Entity::PLACEHOLDER;
         return;
     };
-    picking.prev_hovered = picking.hovered;
-    picking.hovered = *entity;
+    let (camera, camera_transform) = *camera;
+    let to_screen = |position| camera.world_to_viewport(camera_transform, position).ok();
+    if let Some(entity) = closest_pick(
+        geometry.picks(&current, mouse, to_screen),
+        picking.tolerance,
+    ) {
+        picking.hovered = entity;
+    }
 }
 
 pub fn mark_hovered_changing_material(mut commands: Commands, picking: Res<Picking>) {
@@ -114,5 +315,91 @@ pub fn mark_hovered_changing_material(mut commands: Commands, picking: Res<Picki
 }
 
 pub fn reset_picking(mut picking: ResMut<Picking>) {
-    *picking = Picking::default();
+    *picking = Picking {
+        tolerance: picking.tolerance,
+        ..default()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+    use crate::sketching::dot::spawn_temporary_dot;
+    use crate::sketching::line::spawn_line;
+
+    #[test]
+    fn dots_are_picked_before_curves_before_regions() {
+        let [dot, curve, region] = [1, 2, 3].map(Entity::from_raw);
+        let picks = vec![
+            (PickPriority::Region, 0., region),
+            (PickPriority::Curve, 1., curve),
+            (PickPriority::Dot, 5., dot),
+        ];
+        assert_eq!(closest_pick(picks.clone(), PICK_PIXELS), Some(dot));
+        assert_eq!(closest_pick(picks[..2].to_vec(), PICK_PIXELS), Some(curve));
+        assert_eq!(closest_pick(picks[..1].to_vec(), PICK_PIXELS), Some(region));
+        // Only what is within the tolerance counts
+        assert_eq!(closest_pick(picks, 2.), Some(curve));
+    }
+
+    #[test]
+    fn closest_pick_wins_within_a_priority() {
+        let [near, far] = [1, 2].map(Entity::from_raw);
+        let picks = vec![(PickPriority::Dot, 4., far), (PickPriority::Dot, 1., near)];
+        assert_eq!(closest_pick(picks, PICK_PIXELS), Some(near));
+        assert_eq!(closest_pick(Vec::new(), PICK_PIXELS), None);
+    }
+
+    #[test]
+    fn distance_to_segments() {
+        let (start, end) = (Vec2::ZERO, Vec2::new(4., 0.));
+        assert_eq!(distance_to_segment(Vec2::new(2., 3.), start, end), 3.);
+        assert_eq!(distance_to_segment(Vec2::new(-3., 4.), start, end), 5.);
+        assert_eq!(distance_to_segment(Vec2::new(7., 0.), start, end), 3.);
+        // A segment of no length is a point
+        assert_eq!(distance_to_segment(Vec2::new(0., 2.), start, start), 2.);
+    }
+
+    #[test]
+    fn distance_to_rects() {
+        let rect = Rect::new(0., 0., 4., 2.);
+        assert_eq!(distance_to_rect(Vec2::new(1., 1.), rect), 0.);
+        assert_eq!(distance_to_rect(Vec2::new(7., 1.), rect), 3.);
+        assert_eq!(distance_to_rect(Vec2::new(7., 6.), rect), 5.);
+    }
+
+    #[test]
+    fn meshless_temporary_dots_are_picked() {
+        let mut world = World::new();
+        let mut commands = world.commands();
+        let start = spawn_temporary_dot(&mut commands, Vec3::ZERO);
+        let end = spawn_temporary_dot(&mut commands, Vec3::new(10., 0., 0.));
+        let line = spawn_line(&mut commands, start, end);
+        world.flush();
+
+        let mut state = SystemState::<PickableGeometry>::new(&mut world);
+        let geometry = state.get(&world);
+        let picks = geometry.picks(&Current::default(), Vec2::new(4., 1.), |position| {
+            Some(position.truncate())
+        });
+        let is_dot = |dot| {
+            picks
+                .iter()
+                .any(|pick| pick.0 == PickPriority::Dot && pick.2 == dot)
+        };
+        assert!(is_dot(start) && is_dot(end));
+        assert!(picks.contains(&(PickPriority::Curve, 1., line)));
+        assert_eq!(closest_pick(picks.clone(), PICK_PIXELS), Some(start));
+        assert_eq!(closest_pick(picks, 2.), Some(line));
+
+        // Dots of the geometry being sketched follow the mouse, so they are left out
+        let current = Current {
+            dots: vec![start],
+            ..default()
+        };
+        let picks = geometry.picks(&current, Vec2::ZERO, |position| Some(position.truncate()));
+        assert!(picks.iter().all(|pick| pick.2 != start));
+    }
 }
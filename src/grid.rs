@@ -0,0 +1,174 @@
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::schedule::ScheduleSet;
+
+const GRID_LINE_WIDTH: f32 = 1.;
+// Grid lines closer together on screen than this are skipped
+const MIN_GRID_PIXELS: f32 = 8.;
+const MIN_SPACING: f32 = 0.001;
+const MAX_SPACING: f32 = 1000.;
+const MAX_SUBDIVISIONS: u32 = 10;
+// Slightly behind the sketch, so sketched geometry is drawn over it
+const GRID_DEPTH: f32 = -0.01;
+
+// Sketch grid of major lines every `spacing`, each split into `subdivisions`
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    pub spacing: f32,
+    pub subdivisions: u32,
+    pub is_visible: bool,
+    pub is_snapping: bool,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid {
+            spacing: 1.,
+            subdivisions: 4,
+            is_visible: true,
+            is_snapping: false,
+        }
+    }
+}
+
+impl Grid {
+    // Distance between neighbouring grid points
+    pub fn step(&self) -> f32 {
+        self.spacing / self.subdivisions as f32
+    }
+
+    pub fn snap(&self, position: Vec3) -> Vec3 {
+        let step = self.step();
+        ((position.truncate() / step).round() * step).extend(position.z)
+    }
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct GridGizmos;
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Grid::default())
+            .init_gizmo_group::<GridGizmos>()
+            .add_systems(Startup, grid_setup)
+            .add_systems(
+                Update,
+                (
+                    handle_grid_keys.run_if(
+                        input_just_pressed(KeyCode::KeyG)
+                            .or(input_just_pressed(KeyCode::BracketLeft))
+                            .or(input_just_pressed(KeyCode::BracketRight)),
+                    ),
+                    display_grid,
+                )
+                    .chain()
+                    .in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+fn grid_setup(mut gizmo_store: ResMut<GizmoConfigStore>) {
+    let config = gizmo_store.config_mut::<GridGizmos>().0;
+    config.line.width = GRID_LINE_WIDTH;
+}
+
+// G toggles snapping and Shift+G the grid itself.
+// [ and ] halve or double the spacing, or with Shift take away or add a subdivision.
+pub fn handle_grid_keys(keyboard: Res<ButtonInput<KeyCode>>, mut grid: ResMut<Grid>) {
+    if keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
+    let is_shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard.just_pressed(KeyCode::KeyG) {
+        if is_shift {
+            grid.is_visible = !grid.is_visible;
+            info!("Grid visible: {}", grid.is_visible);
+        } else {
+            grid.is_snapping = !grid.is_snapping;
+            info!("Grid snapping: {}", grid.is_snapping);
+        }
+        return;
+    }
+    let is_larger = keyboard.just_pressed(KeyCode::BracketRight);
+    if is_shift {
+        grid.subdivisions = if is_larger {
+            (grid.subdivisions + 1).min(MAX_SUBDIVISIONS)
+        } else {
+            (grid.subdivisions - 1).max(1)
+        };
+    } else {
+        grid.spacing = if is_larger {
+            (grid.spacing * 2.).min(MAX_SPACING)
+        } else {
+            (grid.spacing / 2.).max(MIN_SPACING)
+        };
+    }
+    info!(
+        "Grid spacing: {} in {} subdivisions",
+        grid.spacing, grid.subdivisions
+    );
+}
+
+// Only lines far enough apart on screen are drawn, so the grid stays readable at any zoom
+pub fn display_grid(
+    mut gizmos: Gizmos<GridGizmos>,
+    grid: Res<Grid>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    if !grid.is_visible {
+        return;
+    }
+    let (camera, camera_transform) = *camera;
+    let Some(size) = camera.logical_viewport_size() else {
+        return;
+    };
+    let corners = [Vec2::ZERO, size].map(|corner| {
+        camera
+            .viewport_to_world(camera_transform, corner)
+            .ok()
+            .and_then(|ray| {
+                let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Dir3::Z))?;
+                Some(ray.get_point(distance).truncate())
+            })
+    });
+    let [Some(first), Some(second)] = corners else {
+        return;
+    };
+    let (min, max) = (first.min(second), second.max(first));
+    let pixels_per_unit = size.y / (max.y - min.y);
+
+    let mut major = grid.spacing;
+    while major * pixels_per_unit < MIN_GRID_PIXELS {
+        major *= 2.;
+    }
+    let minor = major / grid.subdivisions as f32;
+    if minor * pixels_per_unit >= MIN_GRID_PIXELS && grid.subdivisions > 1 {
+        draw_grid_lines(&mut gizmos, min, max, minor, color_from_hex(GRID_MINOR));
+    }
+    draw_grid_lines(&mut gizmos, min, max, major, color_from_hex(GRID_MAJOR));
+}
+
+fn draw_grid_lines(gizmos: &mut Gizmos<GridGizmos>, min: Vec2, max: Vec2, step: f32, color: Color) {
+    let first = (min / step).floor() * step;
+    let count = ((max - first) / step).ceil();
+    for i in 0..=count.x as u32 {
+        let x = first.x + i as f32 * step;
+        gizmos.line(
+            Vec3::new(x, min.y, GRID_DEPTH),
+            Vec3::new(x, max.y, GRID_DEPTH),
+            color,
+        );
+    }
+    for i in 0..=count.y as u32 {
+        let y = first.y + i as f32 * step;
+        gizmos.line(
+            Vec3::new(min.x, y, GRID_DEPTH),
+            Vec3::new(max.x, y, GRID_DEPTH),
+            color,
+        );
+    }
+}
@@ -1,15 +1,21 @@
 mod assets;
+mod constraints;
 mod cursor;
+mod grid;
 mod reload;
 mod schedule;
 mod sketching;
+mod snap;
 
 use self::schedule::SchedulePlugin;
 use assets::materials::MaterialsPlugin;
 use bevy::{prelude::*, render::camera::ScalingMode, window::PresentMode};
+use constraints::constraint::ConstraintPlugin;
 use cursor::CursorPlugin;
+use grid::GridPlugin;
 use reload::{ReloadPlugin, Reloadable};
 use sketching::sketch::SketchPlugin;
+use snap::SnapPlugin;
 
 fn main() {
     App::new()
@@ -24,9 +30,12 @@ fn main() {
         }))
         .add_plugins(SchedulePlugin)
         .add_plugins(CursorPlugin)
+        .add_plugins(GridPlugin)
+        .add_plugins(SnapPlugin)
         .add_plugins(ReloadPlugin)
         .add_plugins(SketchPlugin)
         .add_plugins(MaterialsPlugin)
+        .add_plugins(ConstraintPlugin)
         .add_systems(Startup, setup)
         .run();
 }
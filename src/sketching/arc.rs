@@ -0,0 +1,322 @@
+use std::f32::consts::TAU;
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::cursor::{Cursor, Picking};
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+
+use super::circle::circle_through;
+use super::curve::{DotTransforms, SketchCurve, delete_dependent_curves, finalize_curve};
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::delete_selected_entities;
+use super::sketch::{CYCLE_KIND_KEY, Current, DEFAULT_POS, DEFAULT_RESOLUTION, SketchMode};
+
+// Circular arc between two dots, sweeping counterclockwise for a positive angle.
+// Keeping the sweep instead of a center keeps the arc attached to both dots when they move.
+#[derive(Component, Debug, PartialEq)]
+pub struct Arc {
+    pub start: Entity,
+    pub end: Entity,
+    pub sweep: f32,
+}
+
+impl UIMaterialProvider for Arc {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.line.clone()
+    }
+}
+
+impl SketchCurve for Arc {
+    fn dots(&self) -> Vec<Entity> {
+        vec![self.start, self.end]
+    }
+
+    fn points(&self, dots: &DotTransforms) -> Option<Vec<Vec3>> {
+        let start = dots.get(self.start).ok()?.translation;
+        let end = dots.get(self.end).ok()?.translation;
+        Some(arc_points(start, end, self.sweep))
+    }
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArcKind {
+    #[default]
+    ThreePoint,
+    CenterStartEnd,
+}
+
+pub struct ArcPlugin;
+
+impl Plugin for ArcPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ArcKind::default())
+            .add_systems(
+                Update,
+                (
+                    cycle_arc_kind
+                        .run_if(in_state(SketchMode::Arc).and(input_just_pressed(CYCLE_KIND_KEY))),
+                    (
+                        (finalize_dots, finalize_arcs).run_if(is_current_arc_complete),
+                        handle_sketch_arc,
+                    )
+                        .run_if(
+                            in_state(SketchMode::Arc).and(input_just_pressed(MouseButton::Left)),
+                        )
+                        .chain(),
+                    handle_move_current_arc.run_if(in_state(SketchMode::Arc)),
+                )
+                    .chain()
+                    .in_set(ScheduleSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                delete_dependent_curves::<Arc>
+                    .after(delete_selected_entities)
+                    .run_if(input_just_pressed(KeyCode::KeyX))
+                    .in_set(ScheduleSet::DespawnEntities),
+            );
+    }
+}
+
+fn cycle_arc_kind(mut kind: ResMut<ArcKind>) {
+    *kind = match *kind {
+        ArcKind::ThreePoint => ArcKind::CenterStartEnd,
+        ArcKind::CenterStartEnd => ArcKind::ThreePoint,
+    };
+}
+
+pub fn handle_sketch_arc(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    picking: Res<Picking>,
+    kind: Res<ArcKind>,
+    mut arcs: Query<&mut Arc>,
+    dots: DotTransforms,
+) {
+    // Final click: the previewed arc was finalized, so just let go of it
+    if is_arc_complete(&current, *kind) {
+        *current = Current::default();
+        return;
+    }
+
+    let hovered_dot = dots
+        .get(picking.hovered)
+        .ok()
+        .map(|transform| (picking.hovered, transform.translation));
+
+    match (*kind, current.position[0] == DEFAULT_POS) {
+        // Start the arc, previewed as a straight segment until its end is placed
+        (ArcKind::ThreePoint, true) => {
+            let start = match hovered_dot {
+                Some((dot, position)) => {
+                    current.position[0] = position;
+                    dot
+                }
+                None => {
+                    current.position[0] = cursor.position;
+                    let dot = spawn_temporary_dot(&mut commands, cursor.position);
+                    current.dots.push(dot);
+                    dot
+                }
+            };
+            let end = spawn_temporary_dot(&mut commands, cursor.position);
+            current.dots.push(end);
+            let arc = spawn_arc(&mut commands, start, end, 0.);
+            current.curves.push(arc);
+        }
+        // Pin the end, connecting to a hovered dot instead of the temporary one
+        (ArcKind::ThreePoint, false) => {
+            let Some(arc_entity) = current.curves.first() else {
+                return;
+            };
+            let Ok(mut arc) = arcs.get_mut(*arc_entity) else {
+                return;
+            };
+            match hovered_dot {
+                // Ending on the start dot would leave nothing to bend, so the end is not pinned
+                Some((dot, _)) if dot == arc.start => {}
+                Some((dot, position)) => {
+                    current.position[1] = position;
+                    current.dots.retain(|temp_dot| *temp_dot != arc.end);
+                    commands.entity(arc.end).despawn();
+                    arc.end = dot;
+                }
+                None => current.position[1] = cursor.position,
+            }
+        }
+        (ArcKind::CenterStartEnd, true) => {
+            current.position[0] = cursor.position;
+        }
+        // Place the start, which sets the radius, and let the end follow the cursor
+        (ArcKind::CenterStartEnd, false) => {
+            let start = match hovered_dot {
+                Some((dot, _)) => dot,
+                None => {
+                    let dot = spawn_temporary_dot(&mut commands, cursor.position);
+                    current.dots.push(dot);
+                    dot
+                }
+            };
+            let end = spawn_temporary_dot(&mut commands, cursor.position);
+            current.dots.push(end);
+            let arc = spawn_arc(&mut commands, start, end, 0.);
+            current.curves.push(arc);
+        }
+    }
+}
+
+pub fn is_current_arc_complete(current: Res<Current>, kind: Res<ArcKind>) -> bool {
+    is_arc_complete(&current, *kind)
+}
+
+// Whether the next click places the last point of the current arc
+fn is_arc_complete(current: &Current, kind: ArcKind) -> bool {
+    match kind {
+        ArcKind::ThreePoint => current.position[1] != DEFAULT_POS,
+        ArcKind::CenterStartEnd => !current.curves.is_empty(),
+    }
+}
+
+pub fn handle_move_current_arc(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    kind: Res<ArcKind>,
+    mut arcs: Query<&mut Arc>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    let Some(arc_entity) = current.curves.first() else {
+        return;
+    };
+    let Ok(mut arc) = arcs.get_mut(*arc_entity) else {
+        warn!("Could not find currently sketched arc!");
+        return;
+    };
+    let Ok(start) = dots.get(arc.start).map(|transform| transform.translation) else {
+        return;
+    };
+
+    match *kind {
+        ArcKind::ThreePoint if current.position[1] == DEFAULT_POS => {
+            if let Ok(mut transform) = dots.get_mut(arc.end) {
+                transform.translation = cursor.position;
+            }
+        }
+        ArcKind::ThreePoint => {
+            if let Some(sweep) = sweep_through(start, cursor.position, current.position[1]) {
+                arc.sweep = sweep;
+            }
+        }
+        ArcKind::CenterStartEnd => {
+            let center = current.position[0];
+            let radius = center.distance(start);
+            let direction = (cursor.position - center).normalize_or_zero();
+            if direction == Vec3::ZERO {
+                return;
+            }
+            let end = center + direction * radius;
+            arc.sweep = ccw_angle(start - center, end - center);
+            if let Ok(mut transform) = dots.get_mut(arc.end) {
+                transform.translation = end;
+            }
+        }
+    }
+}
+
+pub fn finalize_arcs(
+    mut commands: Commands,
+    current: Res<Current>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    ui_materials: Res<UIMaterials>,
+    arcs: Query<&Arc>,
+    dots: DotTransforms,
+) {
+    for entity in &current.curves {
+        let Ok(arc) = arcs.get(*entity) else {
+            continue;
+        };
+        finalize_curve(
+            &mut commands,
+            &mut meshes,
+            &ui_materials,
+            *entity,
+            arc,
+            &dots,
+        );
+    }
+}
+
+pub fn spawn_arc(commands: &mut Commands, start: Entity, end: Entity, sweep: f32) -> Entity {
+    commands
+        .spawn((
+            Arc { start, end, sweep },
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id()
+}
+
+// Center and radius of the arc, or None when it degenerates to a straight segment
+pub fn arc_center(start: Vec3, end: Vec3, sweep: f32) -> Option<(Vec3, f32)> {
+    let chord = end - start;
+    let half_chord = chord.length() / 2.;
+    if half_chord < f32::EPSILON || sweep.abs() < 1e-4 {
+        return None;
+    }
+    let left = Vec3::new(-chord.y, chord.x, 0.).normalize();
+    let center = (start + end) / 2. + left * (half_chord / (sweep / 2.).tan());
+    Some((center, center.distance(start)))
+}
+
+pub fn arc_points(start: Vec3, end: Vec3, sweep: f32) -> Vec<Vec3> {
+    let Some((center, radius)) = arc_center(start, end, sweep) else {
+        return vec![start, end];
+    };
+    let start_angle = (start.y - center.y).atan2(start.x - center.x);
+    let resolution = ((DEFAULT_RESOLUTION as f32 * sweep.abs() / TAU).ceil() as u32).max(2);
+    (0..=resolution)
+        .map(|i| {
+            let angle = start_angle + sweep * i as f32 / resolution as f32;
+            center + radius * Vec3::new(angle.cos(), angle.sin(), 0.)
+        })
+        .collect()
+}
+
+// Direction of travel at the end dot, which is the chord direction for a straight arc
+pub fn arc_end_tangent(start: Vec3, end: Vec3, sweep: f32) -> Vec3 {
+    let Some((center, _)) = arc_center(start, end, sweep) else {
+        return (end - start).normalize_or_zero();
+    };
+    let radial = end - center;
+    Vec3::new(-radial.y, radial.x, 0.).normalize_or_zero() * sweep.signum()
+}
+
+// Sweep of the arc from start to end that leaves start along the tangent
+pub fn tangent_sweep(start: Vec3, end: Vec3, tangent: Vec3) -> f32 {
+    let chord = end - start;
+    let turn = tangent.x * chord.y - tangent.y * chord.x;
+    2. * turn.atan2(tangent.dot(chord))
+}
+
+// Counterclockwise angle in [0, TAU) from one direction to another
+pub fn ccw_angle(from: Vec3, to: Vec3) -> f32 {
+    let angle = to.y.atan2(to.x) - from.y.atan2(from.x);
+    angle.rem_euclid(TAU)
+}
+
+// Signed sweep of the arc from start to end that passes through a third point
+pub fn sweep_through(start: Vec3, through: Vec3, end: Vec3) -> Option<f32> {
+    let (center, _) = circle_through(start, through, end)?;
+    let ccw_sweep = ccw_angle(start - center, end - center);
+    let ccw_through = ccw_angle(start - center, through - center);
+    if ccw_through < ccw_sweep {
+        Some(ccw_sweep)
+    } else {
+        Some(ccw_sweep - TAU)
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::cursor::{Cursor, Picking};
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+
+use super::coordinate::is_placing_point;
+use super::curve::{DotTransforms, SketchCurve, delete_dependent_curves, finalize_curve};
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::delete_selected_entities;
+use super::sketch::{CYCLE_KIND_KEY, Current, DEFAULT_POS, DEFAULT_RESOLUTION, SketchMode};
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Circle {
+    pub center: Entity,
+    pub radius: f32,
+}
+
+impl UIMaterialProvider for Circle {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.line.clone()
+    }
+}
+
+impl SketchCurve for Circle {
+    fn dots(&self) -> Vec<Entity> {
+        vec![self.center]
+    }
+
+    fn points(&self, dots: &DotTransforms) -> Option<Vec<Vec3>> {
+        let center = dots.get(self.center).ok()?.translation;
+        Some(circle_points(center, self.radius))
+    }
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CircleKind {
+    #[default]
+    CenterRadius,
+    ThreePoint,
+}
+
+pub struct CirclePlugin;
+
+impl Plugin for CirclePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CircleKind::default())
+            .add_systems(
+                Update,
+                (
+                    cycle_circle_kind.run_if(
+                        in_state(SketchMode::Circle).and(input_just_pressed(CYCLE_KIND_KEY)),
+                    ),
+                    (
+                        (finalize_dots, finalize_circles).run_if(is_current_circle_complete),
+                        handle_sketch_circle,
+                    )
+                        .run_if(in_state(SketchMode::Circle).and(is_placing_point))
+                        .chain(),
+                    handle_move_current_circle.run_if(in_state(SketchMode::Circle)),
+                )
+                    .chain()
+                    .in_set(ScheduleSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                delete_dependent_curves::<Circle>
+                    .after(delete_selected_entities)
+                    .run_if(input_just_pressed(KeyCode::KeyX))
+                    .in_set(ScheduleSet::DespawnEntities),
+            );
+    }
+}
+
+fn cycle_circle_kind(mut kind: ResMut<CircleKind>) {
+    *kind = match *kind {
+        CircleKind::CenterRadius => CircleKind::ThreePoint,
+        CircleKind::ThreePoint => CircleKind::CenterRadius,
+    };
+}
+
+pub fn handle_sketch_circle(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    picking: Res<Picking>,
+    kind: Res<CircleKind>,
+    dots: DotTransforms,
+) {
+    // Final click: the previewed circle was finalized, so just let go of it
+    if is_circle_complete(&current, *kind) {
+        *current = Current::default();
+        return;
+    }
+    // Second click of a three-point circle only pins the second point
+    if !current.curves.is_empty() {
+        current.position[1] = cursor.position;
+        return;
+    }
+
+    current.position[0] = cursor.position;
+    let center = match dots.get(picking.hovered) {
+        Ok(transform) if *kind == CircleKind::CenterRadius => {
+            current.position[0] = transform.translation;
+            picking.hovered
+        }
+        _ => {
+            let dot = spawn_temporary_dot(&mut commands, cursor.position);
+            current.dots.push(dot);
+            dot
+        }
+    };
+    let circle = spawn_circle(&mut commands, center, 0.);
+    current.curves.push(circle);
+}
+
+pub fn is_current_circle_complete(current: Res<Current>, kind: Res<CircleKind>) -> bool {
+    is_circle_complete(&current, *kind)
+}
+
+// Whether the next click places the last point of the current circle
+fn is_circle_complete(current: &Current, kind: CircleKind) -> bool {
+    match kind {
+        CircleKind::CenterRadius => current.position[0] != DEFAULT_POS,
+        CircleKind::ThreePoint => current.position[1] != DEFAULT_POS,
+    }
+}
+
+pub fn handle_move_current_circle(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    kind: Res<CircleKind>,
+    mut circles: Query<&mut Circle>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    let Some(circle_entity) = current.curves.first() else {
+        return;
+    };
+    let Ok(mut circle) = circles.get_mut(*circle_entity) else {
+        warn!("Could not find currently sketched circle!");
+        return;
+    };
+
+    let (center, radius) = match *kind {
+        CircleKind::CenterRadius => (
+            current.position[0],
+            current.position[0].distance(cursor.position),
+        ),
+        // Until the second point is pinned, preview the circle on the diameter
+        CircleKind::ThreePoint if current.position[1] == DEFAULT_POS => (
+            (current.position[0] + cursor.position) / 2.,
+            current.position[0].distance(cursor.position) / 2.,
+        ),
+        CircleKind::ThreePoint => {
+            let Some(circumcircle) =
+                circle_through(current.position[0], current.position[1], cursor.position)
+            else {
+                return;
+            };
+            circumcircle
+        }
+    };
+
+    circle.radius = radius;
+    if let Ok(mut transform) = dots.get_mut(circle.center) {
+        transform.translation = center;
+    }
+}
+
+pub fn finalize_circles(
+    mut commands: Commands,
+    current: Res<Current>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    ui_materials: Res<UIMaterials>,
+    circles: Query<&Circle>,
+    dots: DotTransforms,
+) {
+    for entity in &current.curves {
+        let Ok(circle) = circles.get(*entity) else {
+            continue;
+        };
+        finalize_curve(
+            &mut commands,
+            &mut meshes,
+            &ui_materials,
+            *entity,
+            circle,
+            &dots,
+        );
+    }
+}
+
+pub fn spawn_circle(commands: &mut Commands, center: Entity, radius: f32) -> Entity {
+    commands
+        .spawn((
+            Circle { center, radius },
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id()
+}
+
+pub fn circle_points(center: Vec3, radius: f32) -> Vec<Vec3> {
+    (0..=DEFAULT_RESOLUTION)
+        .map(|i| {
+            let angle = i as f32 / DEFAULT_RESOLUTION as f32 * std::f32::consts::TAU;
+            center + radius * Vec3::new(angle.cos(), angle.sin(), 0.)
+        })
+        .collect()
+}
+
+// Center and radius of the circle through three points, if they are not collinear
+pub fn circle_through(a: Vec3, b: Vec3, c: Vec3) -> Option<(Vec3, f32)> {
+    let ab = b - a;
+    let ac = c - a;
+    let denominator = 2. * (ab.x * ac.y - ab.y * ac.x);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let ab_sq = ab.length_squared();
+    let ac_sq = ac.length_squared();
+    let offset =
+        Vec3::new(ac.y * ab_sq - ab.y * ac_sq, ab.x * ac_sq - ac.x * ab_sq, 0.) / denominator;
+    Some((a + offset, offset.length()))
+}
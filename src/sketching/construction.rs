@@ -0,0 +1,72 @@
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::materials::ChangingMaterial;
+use crate::schedule::ScheduleSet;
+
+use super::arc::Arc;
+use super::circle::Circle;
+use super::ellipse::{Ellipse, EllipticalArc};
+use super::line::Line;
+use super::selection::Selected;
+use super::size::LINE_WIDTH;
+use super::spline::Spline;
+
+// Only curves can be construction geometry, not dots or constraint glyphs
+type SelectedGeometry = (
+    With<Selected>,
+    Or<(
+        With<Line>,
+        With<Circle>,
+        With<Arc>,
+        With<Spline>,
+        With<Ellipse>,
+        With<EllipticalArc>,
+    )>,
+);
+
+// Reference geometry that is drawn dashed and is not part of the sketched part
+#[derive(Component, Default)]
+#[component(storage = "SparseSet")]
+pub struct Construction;
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct ConstructionGizmos;
+
+pub struct ConstructionPlugin;
+
+impl Plugin for ConstructionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<ConstructionGizmos>()
+            .add_systems(Startup, construction_setup)
+            .add_systems(
+                Update,
+                toggle_construction
+                    .run_if(input_just_pressed(KeyCode::KeyQ))
+                    .in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+fn construction_setup(mut gizmo_store: ResMut<GizmoConfigStore>) {
+    let config = gizmo_store.config_mut::<ConstructionGizmos>().0;
+    config.line.width = LINE_WIDTH;
+    config.line.style = GizmoLineStyle::Dashed {
+        gap_scale: 3.,
+        line_scale: 5.,
+    };
+}
+
+pub fn toggle_construction(
+    mut commands: Commands,
+    query: Query<(Entity, Has<Construction>), SelectedGeometry>,
+) {
+    for (entity, is_construction) in query.iter() {
+        if is_construction {
+            commands.entity(entity).remove::<Construction>();
+        } else {
+            commands.entity(entity).insert(Construction);
+        }
+        commands.entity(entity).insert(ChangingMaterial);
+    }
+}
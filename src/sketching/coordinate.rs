@@ -0,0 +1,269 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::ecs::system::SystemParam;
+use bevy::input::InputSystem;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::constraints::expression::{Expression, ExpressionError, Unit};
+use crate::constraints::parameter::{Parameters, type_text};
+use crate::cursor::{Cursor, Picking, mark_hovered_changing_material};
+use crate::schedule::ScheduleSet;
+use crate::snap::snap_line_angle;
+
+use super::dot::Dot;
+use super::line::Line;
+use super::sketch::{Current, DEFAULT_POS, SketchMode};
+
+const COORDINATE_FONT_SIZE: f32 = 14.;
+const COORDINATE_OFFSET: Vec2 = Vec2::new(14., -28.);
+// Typing one of these while sketching starts a point instead of triggering a shortcut
+const COORDINATE_START: [char; 13] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '.', '-', '@',
+];
+
+// Point typed while sketching, like `25<30`, `@10,5` or `10,5`
+#[derive(Resource, Debug, Default)]
+pub struct CoordinateInput {
+    pub is_open: bool,
+    pub text: String,
+    // Entered text, resolved once the sketch around it is known
+    pub submitted: Option<String>,
+    // Resolved point the cursor is held at until it has been placed
+    pub point: Option<Vec3>,
+    pub is_previewed: bool,
+}
+
+// A point placed without the mouse, handled by the tools like a click at the cursor
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct PlacePoint(pub Vec3);
+
+#[derive(Component)]
+pub struct CoordinateLabel;
+
+// Where relative points are measured from: the start of the line being drawn,
+// the rectangle anchor or the last point of the circle
+#[derive(SystemParam)]
+pub struct TypedBase<'w, 's> {
+    mode: Res<'w, State<SketchMode>>,
+    current: Res<'w, Current>,
+    lines: Query<'w, 's, &'static Line>,
+    dots: Query<'w, 's, &'static Transform, With<Dot>>,
+}
+
+impl TypedBase<'_, '_> {
+    fn position(&self) -> Vec3 {
+        let current = &self.current;
+        let base = match self.mode.get() {
+            SketchMode::Line => current
+                .lines
+                .first()
+                .and_then(|line| self.lines.get(*line).ok())
+                .and_then(|line| self.dots.get(line.start).ok())
+                .map(|transform| transform.translation),
+            SketchMode::Rectangle if !current.lines.is_empty() => Some(current.position[0]),
+            SketchMode::Circle if !current.curves.is_empty() => {
+                Some(if current.position[1] != DEFAULT_POS {
+                    current.position[1]
+                } else {
+                    current.position[0]
+                })
+            }
+            _ => None,
+        };
+        base.unwrap_or(Vec3::ZERO)
+    }
+}
+
+pub struct CoordinatePlugin;
+
+impl Plugin for CoordinatePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CoordinateInput::default())
+            .add_event::<PlacePoint>()
+            .add_systems(Startup, spawn_coordinate_label)
+            .add_systems(PreUpdate, handle_coordinate_input.after(InputSystem))
+            .add_systems(
+                Update,
+                place_typed_point
+                    .after(snap_line_angle)
+                    .before(mark_hovered_changing_material)
+                    .in_set(ScheduleSet::UserInput),
+            )
+            .add_systems(
+                Update,
+                update_coordinate_label.in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+fn spawn_coordinate_label(mut commands: Commands) {
+    commands.spawn((
+        CoordinateLabel,
+        Text::default(),
+        TextFont {
+            font_size: COORDINATE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(color_from_hex(GOLD_YELLOW)),
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+    ));
+}
+
+fn is_typing_mode(mode: SketchMode) -> bool {
+    matches!(
+        mode,
+        SketchMode::Line | SketchMode::Rectangle | SketchMode::Circle
+    )
+}
+
+pub fn handle_coordinate_input(
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    mut events: EventReader<KeyboardInput>,
+    mut input: ResMut<CoordinateInput>,
+    mode: Res<State<SketchMode>>,
+) {
+    if input.is_open {
+        type_text(&mut events, &keyboard, &mut input.text);
+    } else {
+        let opening = events.read().find_map(|event| match &event.logical_key {
+            Key::Character(characters)
+                if event.state.is_pressed() && characters.starts_with(COORDINATE_START) =>
+            {
+                Some(characters.to_string())
+            }
+            _ => None,
+        });
+        events.clear();
+        let is_modified = keyboard.any_pressed([
+            KeyCode::AltLeft,
+            KeyCode::AltRight,
+            KeyCode::ControlLeft,
+            KeyCode::ControlRight,
+        ]);
+        let Some(opening) = opening else {
+            return;
+        };
+        if is_modified || !is_typing_mode(*mode.get()) {
+            return;
+        }
+        input.is_open = true;
+        input.text = opening;
+    }
+    if keyboard.just_pressed(KeyCode::Escape) {
+        input.is_open = false;
+        input.text.clear();
+    } else if keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        input.submitted = Some(std::mem::take(&mut input.text));
+        input.is_open = false;
+    }
+    keyboard.clear();
+}
+
+// An entered point is first only previewed, then placed on the next frame, so every tool
+// finalizes exactly the geometry it showed
+pub fn place_typed_point(
+    mut input: ResMut<CoordinateInput>,
+    mut placed: EventWriter<PlacePoint>,
+    parameters: Res<Parameters>,
+    mut cursor: ResMut<Cursor>,
+    mut picking: ResMut<Picking>,
+    base: TypedBase,
+) {
+    if let Some(text) = input.submitted.take() {
+        match resolve_point(&parameters, &text, base.position(), cursor.position) {
+            Ok(point) => input.point = Some(point),
+            Err(error) => warn!("Invalid point {:?}: {}", text, error),
+        }
+    }
+    let Some(point) = input.point else {
+        return;
+    };
+    cursor.position = point;
+    // A typed point is placed as is, never connected to whatever the mouse is over
+    picking.hovered = Entity::PLACEHOLDER;
+    if input.is_previewed {
+        placed.write(PlacePoint(point));
+        input.point = None;
+        input.is_previewed = false;
+    } else {
+        input.is_previewed = true;
+    }
+}
+
+// Tools place their points on a click or on a point placed without the mouse
+pub fn is_placing_point(
+    mut placed: EventReader<PlacePoint>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) -> bool {
+    // Always read, so an event is never handled twice
+    let is_placed = placed.read().count() > 0;
+    is_placed || mouse.just_pressed(MouseButton::Left)
+}
+
+// `x,y` is absolute and `@x,y` relative to the base. `length<angle` is always relative, and a
+// single length goes from the base toward the cursor.
+fn resolve_point(
+    parameters: &Parameters,
+    text: &str,
+    base: Vec3,
+    toward: Vec3,
+) -> Result<Vec3, ExpressionError> {
+    let text = text.trim();
+    let (is_relative, text) = match text.strip_prefix('@') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    if let Some((length, angle)) = text.split_once('<') {
+        let length = evaluate(parameters, length, Unit::Length)?;
+        let angle = evaluate(parameters, angle, Unit::Angle)?.to_radians();
+        return Ok(base + (Vec2::from_angle(angle) * length).extend(0.));
+    }
+    if let Some((x, y)) = text.split_once(',') {
+        let offset = Vec3::new(
+            evaluate(parameters, x, Unit::Length)?,
+            evaluate(parameters, y, Unit::Length)?,
+            0.,
+        );
+        return Ok(if is_relative { base + offset } else { offset });
+    }
+    let length = evaluate(parameters, text, Unit::Length)?;
+    let direction = (toward - base)
+        .truncate()
+        .try_normalize()
+        .unwrap_or(Vec2::X);
+    Ok(base + (direction * length).extend(0.))
+}
+
+fn evaluate(parameters: &Parameters, text: &str, unit: Unit) -> Result<f32, ExpressionError> {
+    let quantity = parameters.evaluate(&Expression::parse(text)?)?;
+    if !quantity.fits(unit) {
+        return Err(ExpressionError::UnitMismatch);
+    }
+    Ok(quantity.value as f32)
+}
+
+pub fn update_coordinate_label(
+    input: Res<CoordinateInput>,
+    windows: Query<&Window>,
+    label: Single<(&mut Text, &mut Node), With<CoordinateLabel>>,
+) {
+    let (mut text, mut node) = label.into_inner();
+    text.0 = if input.is_open {
+        format!("> {}_", input.text)
+    } else {
+        String::new()
+    };
+    let Some(position) = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    node.left = Val::Px(position.x + COORDINATE_OFFSET.x);
+    node.top = Val::Px(position.y + COORDINATE_OFFSET.y);
+}
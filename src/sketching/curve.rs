@@ -0,0 +1,146 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, MeshAabb, PrimitiveTopology};
+use bevy::render::primitives::Aabb;
+
+use crate::assets::colors::*;
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::assets::visibility::MESH_VISIBILITY;
+
+use super::construction::{Construction, ConstructionGizmos};
+use super::dot::Dot;
+use super::selection::Selected;
+use super::size::CURVE_MESH_WIDTH;
+use super::sketch::Moving;
+
+pub type DotTransforms<'w, 's> = Query<'w, 's, &'static Transform, With<Dot>>;
+
+type ChangedDot = (With<Dot>, Changed<Transform>);
+type DotNotMoving = (With<Dot>, Without<Moving>);
+
+// Sketch geometry that is drawn as a polyline through positions derived from its dots
+pub trait SketchCurve: Component + UIMaterialProvider {
+    fn dots(&self) -> Vec<Entity>;
+
+    fn points(&self, dots: &DotTransforms) -> Option<Vec<Vec3>>;
+}
+
+pub fn finalize_curve<T: SketchCurve>(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    ui_materials: &Res<UIMaterials>,
+    curve_entity: Entity,
+    curve: &T,
+    dots: &DotTransforms,
+) {
+    let points = curve.points(dots).unwrap_or_default();
+    commands.entity(curve_entity).insert((
+        Mesh3d(meshes.add(curve_mesh(&points))),
+        MeshMaterial3d(T::get_material(ui_materials)),
+        MESH_VISIBILITY,
+    ));
+}
+
+pub fn display_curves<T: SketchCurve>(
+    mut gizmos: Gizmos,
+    mut construction_gizmos: Gizmos<ConstructionGizmos>,
+    curves: Query<(&T, Has<Construction>)>,
+    dots: DotTransforms,
+) {
+    for (curve, is_construction) in curves.iter() {
+        let Some(points) = curve.points(&dots) else {
+            continue;
+        };
+        if is_construction {
+            construction_gizmos.linestrip(points, color_from_hex(CONSTRUCTION));
+        } else {
+            gizmos.linestrip(points, color_from_hex(LINE));
+        }
+    }
+}
+
+// Rebuild meshes of curves whose own data or dots changed, so picking follows the drawing
+pub fn update_curve_meshes<T: SketchCurve>(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut curves: Query<(Ref<T>, &Mesh3d, &mut Aabb)>,
+    dots: DotTransforms,
+    changed_dots: Query<(), ChangedDot>,
+) {
+    for (curve, mesh_handle, mut aabb) in curves.iter_mut() {
+        let is_dot_changed = curve.dots().iter().any(|dot| changed_dots.contains(*dot));
+        if !curve.is_changed() && !is_dot_changed {
+            continue;
+        }
+        let Some(points) = curve.points(&dots) else {
+            continue;
+        };
+        let Some(mesh) = meshes.get_mut(&mesh_handle.0) else {
+            continue;
+        };
+        *mesh = curve_mesh(&points);
+        // Bounds are only computed once by bevy, but ray casts cull against them
+        if let Some(bounds) = mesh.compute_aabb() {
+            *aabb = bounds;
+        }
+    }
+}
+
+pub fn mark_moving_curves<T: SketchCurve>(
+    mut commands: Commands,
+    curves: Query<&T, With<Selected>>,
+    dots: Query<Entity, DotNotMoving>,
+) {
+    for curve in &curves {
+        for dot in curve.dots() {
+            if let Ok(dot) = dots.get(dot) {
+                commands.entity(dot).insert(Moving);
+            }
+        }
+    }
+}
+
+// Delete curves if any of their dots have been deleted
+pub fn delete_dependent_curves<T: SketchCurve>(
+    mut commands: Commands,
+    curves: Query<(Entity, &T)>,
+    dots: Query<&Dot>,
+) {
+    for (entity, curve) in curves.iter() {
+        if curve.dots().iter().any(|dot| !dots.contains(*dot)) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Flat ribbon along the points in the sketch plane, wound to face the camera
+pub fn curve_mesh(points: &[Vec3]) -> Mesh {
+    let half_width = CURVE_MESH_WIDTH / 2.;
+    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(points.len() * 2);
+    let mut indices: Vec<u32> = Vec::with_capacity(points.len() * 6);
+
+    for i in 0..points.len() {
+        let prev = points[i.saturating_sub(1)];
+        let next = points[(i + 1).min(points.len() - 1)];
+        let tangent = (next - prev).normalize_or_zero();
+        let normal = Vec3::new(-tangent.y, tangent.x, 0.) * half_width;
+        positions.push((points[i] - normal).to_array());
+        positions.push((points[i] + normal).to_array());
+
+        if i > 0 {
+            let right = (2 * i - 2) as u32;
+            let left = right + 1;
+            let next_right = right + 2;
+            let next_left = right + 3;
+            indices.extend([right, next_right, next_left, right, next_left, left]);
+        }
+    }
+    let normals = vec![[0., 0., 1.]; positions.len()];
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(indices))
+}
@@ -0,0 +1,357 @@
+use std::f32::consts::TAU;
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::cursor::{Cursor, Picking};
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+
+use super::curve::{DotTransforms, SketchCurve, delete_dependent_curves, finalize_curve};
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::delete_selected_entities;
+use super::sketch::{CYCLE_KIND_KEY, Current, DEFAULT_POS, DEFAULT_RESOLUTION, SketchMode};
+
+// Center dot plus handle dots at the end of the major axis and on the minor axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EllipseAxes {
+    pub center: Entity,
+    pub major: Entity,
+    pub minor: Entity,
+}
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Ellipse {
+    pub axes: EllipseAxes,
+}
+
+// Part of an ellipse, running counterclockwise between two parametric angles
+#[derive(Component, Debug, PartialEq)]
+pub struct EllipticalArc {
+    pub axes: EllipseAxes,
+    pub start_angle: f32,
+    pub end_angle: f32,
+}
+
+impl EllipseAxes {
+    fn dots(&self) -> Vec<Entity> {
+        vec![self.center, self.major, self.minor]
+    }
+
+    // Center and the two semi-axis vectors
+    fn frame(&self, dots: &DotTransforms) -> Option<(Vec3, Vec3, Vec3)> {
+        let center = dots.get(self.center).ok()?.translation;
+        let major = dots.get(self.major).ok()?.translation - center;
+        let minor = dots.get(self.minor).ok()?.translation - center;
+        let minor_direction = Vec3::new(-major.y, major.x, 0.).normalize_or_zero();
+        Some((
+            center,
+            major,
+            minor_direction * minor.dot(minor_direction).abs(),
+        ))
+    }
+}
+
+impl UIMaterialProvider for Ellipse {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.line.clone()
+    }
+}
+
+impl UIMaterialProvider for EllipticalArc {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.line.clone()
+    }
+}
+
+impl SketchCurve for Ellipse {
+    fn dots(&self) -> Vec<Entity> {
+        self.axes.dots()
+    }
+
+    fn points(&self, dots: &DotTransforms) -> Option<Vec<Vec3>> {
+        let (center, major, minor) = self.axes.frame(dots)?;
+        Some(ellipse_points(center, major, minor, 0., TAU))
+    }
+}
+
+impl SketchCurve for EllipticalArc {
+    fn dots(&self) -> Vec<Entity> {
+        self.axes.dots()
+    }
+
+    fn points(&self, dots: &DotTransforms) -> Option<Vec<Vec3>> {
+        let (center, major, minor) = self.axes.frame(dots)?;
+        Some(ellipse_points(
+            center,
+            major,
+            minor,
+            self.start_angle,
+            self.end_angle,
+        ))
+    }
+}
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EllipseKind {
+    #[default]
+    Full,
+    Arc,
+}
+
+pub struct EllipsePlugin;
+
+impl Plugin for EllipsePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EllipseKind::default())
+            .add_systems(
+                Update,
+                (
+                    cycle_ellipse_kind.run_if(
+                        in_state(SketchMode::Ellipse).and(input_just_pressed(CYCLE_KIND_KEY)),
+                    ),
+                    (
+                        (finalize_dots, finalize_ellipses).run_if(is_current_ellipse_complete),
+                        handle_sketch_ellipse,
+                    )
+                        .run_if(
+                            in_state(SketchMode::Ellipse)
+                                .and(input_just_pressed(MouseButton::Left)),
+                        )
+                        .chain(),
+                    handle_move_current_ellipse.run_if(in_state(SketchMode::Ellipse)),
+                )
+                    .chain()
+                    .in_set(ScheduleSet::EntityUpdates),
+            )
+            .add_systems(
+                Update,
+                (
+                    delete_dependent_curves::<Ellipse>,
+                    delete_dependent_curves::<EllipticalArc>,
+                )
+                    .after(delete_selected_entities)
+                    .run_if(input_just_pressed(KeyCode::KeyX))
+                    .in_set(ScheduleSet::DespawnEntities),
+            );
+    }
+}
+
+fn cycle_ellipse_kind(mut kind: ResMut<EllipseKind>) {
+    *kind = match *kind {
+        EllipseKind::Full => EllipseKind::Arc,
+        EllipseKind::Arc => EllipseKind::Full,
+    };
+}
+
+// Clicks place the center, the major axis, the minor axis and, for arcs, the end
+pub fn handle_sketch_ellipse(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    picking: Res<Picking>,
+    kind: Res<EllipseKind>,
+    mut elliptical_arcs: Query<&mut EllipticalArc>,
+    dots: DotTransforms,
+) {
+    // Final click: the previewed ellipse was finalized, so just let go of it
+    if is_ellipse_complete(&current, *kind) {
+        *current = Current::default();
+        return;
+    }
+
+    if current.position[0] == DEFAULT_POS {
+        let center = match dots.get(picking.hovered) {
+            Ok(transform) => {
+                current.position[0] = transform.translation;
+                picking.hovered
+            }
+            Err(_) => {
+                current.position[0] = cursor.position;
+                let dot = spawn_temporary_dot(&mut commands, cursor.position);
+                current.dots.push(dot);
+                dot
+            }
+        };
+        let major = spawn_temporary_dot(&mut commands, cursor.position);
+        let minor = spawn_temporary_dot(&mut commands, cursor.position);
+        current.dots.extend([major, minor]);
+        let axes = EllipseAxes {
+            center,
+            major,
+            minor,
+        };
+        let ellipse = match *kind {
+            EllipseKind::Full => spawn_ellipse(&mut commands, axes),
+            EllipseKind::Arc => spawn_elliptical_arc(&mut commands, axes, 0., TAU),
+        };
+        current.curves.push(ellipse);
+    } else if current.position[1] == DEFAULT_POS {
+        current.position[1] = cursor.position;
+    } else {
+        // The minor axis click also marks where the elliptical arc starts
+        current.position[2] = cursor.position;
+        let Some(mut arc) = current
+            .curves
+            .first()
+            .and_then(|arc| elliptical_arcs.get_mut(*arc).ok())
+        else {
+            return;
+        };
+        let Some((center, major, minor)) = arc.axes.frame(&dots) else {
+            return;
+        };
+        arc.start_angle = ellipse_angle(center, major, minor, cursor.position);
+        arc.end_angle = arc.start_angle;
+    }
+}
+
+pub fn is_current_ellipse_complete(current: Res<Current>, kind: Res<EllipseKind>) -> bool {
+    is_ellipse_complete(&current, *kind)
+}
+
+// Whether the next click places the last point of the current ellipse
+fn is_ellipse_complete(current: &Current, kind: EllipseKind) -> bool {
+    match kind {
+        EllipseKind::Full => current.position[1] != DEFAULT_POS,
+        EllipseKind::Arc => current.position[2] != DEFAULT_POS,
+    }
+}
+
+pub fn handle_move_current_ellipse(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    ellipses: Query<&Ellipse>,
+    mut elliptical_arcs: Query<&mut EllipticalArc>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    let Some(curve) = current.curves.first() else {
+        return;
+    };
+    let axes = if let Ok(ellipse) = ellipses.get(*curve) {
+        ellipse.axes
+    } else if let Ok(arc) = elliptical_arcs.get(*curve) {
+        arc.axes
+    } else {
+        warn!("Could not find currently sketched ellipse!");
+        return;
+    };
+    let center = current.position[0];
+
+    if current.position[1] == DEFAULT_POS {
+        // Preview with a minor axis of half the major axis until it is placed
+        let major = cursor.position - center;
+        let minor = Vec3::new(-major.y, major.x, 0.) / 2.;
+        set_dot_position(&mut dots, axes.major, center + major);
+        set_dot_position(&mut dots, axes.minor, center + minor);
+    } else if current.position[2] == DEFAULT_POS {
+        let major = current.position[1] - center;
+        let minor_direction = Vec3::new(-major.y, major.x, 0.).normalize_or_zero();
+        let minor = minor_direction * (cursor.position - center).dot(minor_direction).abs();
+        set_dot_position(&mut dots, axes.minor, center + minor);
+    } else if let Ok(mut arc) = elliptical_arcs.get_mut(*curve) {
+        let major = current.position[1] - center;
+        let minor = dots
+            .get(axes.minor)
+            .map(|transform| transform.translation - center)
+            .unwrap_or(major);
+        let end_angle = ellipse_angle(center, major, minor, cursor.position);
+        arc.end_angle = arc.start_angle + (end_angle - arc.start_angle).rem_euclid(TAU);
+    }
+}
+
+fn set_dot_position(dots: &mut Query<&mut Transform, With<Dot>>, dot: Entity, position: Vec3) {
+    if let Ok(mut transform) = dots.get_mut(dot) {
+        transform.translation = position;
+    }
+}
+
+pub fn finalize_ellipses(
+    mut commands: Commands,
+    current: Res<Current>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    ui_materials: Res<UIMaterials>,
+    ellipses: Query<&Ellipse>,
+    elliptical_arcs: Query<&EllipticalArc>,
+    dots: DotTransforms,
+) {
+    for entity in &current.curves {
+        if let Ok(ellipse) = ellipses.get(*entity) {
+            finalize_curve(
+                &mut commands,
+                &mut meshes,
+                &ui_materials,
+                *entity,
+                ellipse,
+                &dots,
+            );
+        } else if let Ok(arc) = elliptical_arcs.get(*entity) {
+            finalize_curve(
+                &mut commands,
+                &mut meshes,
+                &ui_materials,
+                *entity,
+                arc,
+                &dots,
+            );
+        }
+    }
+}
+
+pub fn spawn_ellipse(commands: &mut Commands, axes: EllipseAxes) -> Entity {
+    commands
+        .spawn((
+            Ellipse { axes },
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id()
+}
+
+pub fn spawn_elliptical_arc(
+    commands: &mut Commands,
+    axes: EllipseAxes,
+    start_angle: f32,
+    end_angle: f32,
+) -> Entity {
+    commands
+        .spawn((
+            EllipticalArc {
+                axes,
+                start_angle,
+                end_angle,
+            },
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id()
+}
+
+pub fn ellipse_points(
+    center: Vec3,
+    major: Vec3,
+    minor: Vec3,
+    start_angle: f32,
+    end_angle: f32,
+) -> Vec<Vec3> {
+    let sweep = end_angle - start_angle;
+    let resolution = ((DEFAULT_RESOLUTION as f32 * sweep.abs() / TAU).ceil() as u32).max(2);
+    (0..=resolution)
+        .map(|i| {
+            let angle = start_angle + sweep * i as f32 / resolution as f32;
+            center + major * angle.cos() + minor * angle.sin()
+        })
+        .collect()
+}
+
+// Parametric angle of the point on the ellipse in the direction of a position
+pub fn ellipse_angle(center: Vec3, major: Vec3, minor: Vec3, position: Vec3) -> f32 {
+    let offset = position - center;
+    let along_major = offset.dot(major) / major.length_squared().max(f32::EPSILON);
+    let along_minor = offset.dot(minor) / minor.length_squared().max(f32::EPSILON);
+    along_minor.atan2(along_major)
+}
@@ -5,16 +5,25 @@ use bevy::prelude::*;
 use crate::assets::colors::*;
 use crate::assets::materials::{UIMaterialProvider, UIMaterials};
 use crate::assets::visibility::MESH_VISIBILITY;
+use crate::constraints::constraint::{Constraint, is_ctrl_pressed, spawn_constraint};
+use crate::constraints::solver::ConstraintSolver;
 use crate::cursor::{Cursor, Picking, reset_picking};
 use crate::reload::{ReloadLevel, Reloadable};
 use crate::schedule::ScheduleSet;
+use crate::snap::{Snap, SnapKind};
 
+use super::arc::{Arc, arc_end_tangent, finalize_arcs, spawn_arc, tangent_sweep};
+use super::circle::Circle;
+use super::construction::{Construction, ConstructionGizmos};
+use super::coordinate::is_placing_point;
+use super::curve::DotTransforms;
 use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
 use super::selection::Selected;
 use super::size::LINE_MESH_WIDTH;
 use super::sketch::{Checked, Current, Moving, SketchMode};
 
 type DotNotMoving = (With<Dot>, Without<Line>, Without<Moving>);
+type HoverableCurves<'w, 's> = Query<'w, 's, (), Or<(With<Line>, With<Circle>, With<Arc>)>>;
 
 #[derive(Component, Debug, PartialEq)]
 pub struct Line {
@@ -46,14 +55,18 @@ impl Plugin for LinePlugin {
                     (
                         finalize_dots,
                         finalize_lines,
+                        finalize_arcs,
+                        update_chain_tangent,
+                        check_pending_line,
                         handle_sketch_line,
+                        attach_dot_to_hovered_curve.run_if(not(is_ctrl_pressed)),
                         clear_redundant,
                     )
-                        .run_if(
-                            in_state(SketchMode::Line).and(input_just_pressed(MouseButton::Left)),
-                        )
+                        .run_if(in_state(SketchMode::Line).and(is_placing_point))
+                        .chain(),
+                    (handle_tangent_arc_modifier, handle_move_current_line)
+                        .run_if(in_state(SketchMode::Line))
                         .chain(),
-                    handle_move_current_line.run_if(in_state(SketchMode::Line)),
                 )
                     .chain()
                     .in_set(ScheduleSet::EntityUpdates),
@@ -69,27 +82,43 @@ impl Plugin for LinePlugin {
     }
 }
 
+// The segment being finished is checked for duplicates once the click is handled
+pub fn check_pending_line(current: Res<Current>, mut checked: ResMut<Checked>) {
+    checked.lines.clear();
+    checked.lines.push(
+        current
+            .lines
+            .first()
+            .copied()
+            .unwrap_or(Entity::PLACEHOLDER),
+    );
+}
+
 pub fn handle_sketch_line(
     mut commands: Commands,
     cursor: Res<Cursor>,
     mut current: ResMut<Current>,
     picking: Res<Picking>,
-    mut checked: ResMut<Checked>,
-    lines: Query<&mut Line>,
+    mut lines: Query<&mut Line>,
+    mut arcs: Query<&mut Arc>,
+    dots: Query<(), With<Dot>>,
 ) {
     let start_dot: Entity;
-    let mut prev_line = Entity::PLACEHOLDER;
-    if !current.lines.is_empty() {
-        prev_line = current.lines[0];
-    }
 
     let current_empty = current.dots.is_empty();
-    let hover_empty = picking.hovered == Entity::PLACEHOLDER;
+    // Only dots can be connected to, other hovered geometry is sketched over
+    let hover_empty = !dots.contains(picking.hovered);
+
+    // Only a continued chain has a previous segment to be tangent to
+    if current_empty {
+        current.tangent = None;
+    }
 
     // New chain starting with new dot
     if current_empty && hover_empty {
         start_dot = spawn_temporary_dot(&mut commands, cursor.position);
         current.dots.push(start_dot);
+        current.chain_start = Some(start_dot);
     }
     // Continue chain with new dot
     else if !current_empty && hover_empty {
@@ -100,13 +129,19 @@ pub fn handle_sketch_line(
     else if current_empty && !hover_empty {
         start_dot = picking.hovered;
         current.dots.clear();
+        current.chain_start = Some(start_dot);
     }
     // Continue chain with existing dot
     else {
-        let temp_dot = swap_line_end(picking.hovered, &mut current, lines);
+        let temp_dot = swap_line_end(picking.hovered, &mut current, &mut lines, &mut arcs);
+        commands.entity(temp_dot).despawn();
+        // Clicking the dot that started the chain closes the loop and ends the chain
+        if current.chain_start == Some(picking.hovered) {
+            *current = Current::default();
+            return;
+        }
         start_dot = picking.hovered;
         current.dots.clear();
-        commands.entity(temp_dot).despawn();
     }
 
     let end_dot = spawn_temporary_dot(&mut commands, cursor.position);
@@ -114,10 +149,36 @@ pub fn handle_sketch_line(
     current.dots.push(end_dot);
 
     current.lines.clear();
+    current.curves.clear();
     let line = spawn_line(&mut commands, start_dot, end_dot);
     current.lines.push(line);
-    checked.lines.clear();
-    checked.lines.push(prev_line);
+}
+
+// A dot dropped onto a line, circle or arc stays on it, instead of only looking attached.
+// Snapped to the middle of a line, it stays in the middle.
+pub fn attach_dot_to_hovered_curve(
+    mut commands: Commands,
+    current: Res<Current>,
+    picking: Res<Picking>,
+    snap: Res<Snap>,
+    mut solver: ResMut<ConstraintSolver>,
+    lines: Query<&Line>,
+    curves: HoverableCurves,
+) {
+    if !curves.contains(picking.hovered) {
+        return;
+    }
+    // The dot just placed starts the new segment
+    let Some(line) = current.lines.first().and_then(|line| lines.get(*line).ok()) else {
+        return;
+    };
+    let constraint = if snap.kind == Some(SnapKind::Midpoint) && snap.target == picking.hovered {
+        Constraint::Midpoint(line.start, picking.hovered)
+    } else {
+        Constraint::PointOnCurve(line.start, picking.hovered)
+    };
+    spawn_constraint(&mut commands, constraint);
+    solver.is_dirty = true;
 }
 
 pub fn clear_redundant(
@@ -160,6 +221,7 @@ pub fn handle_move_current_line(
     cursor: Res<Cursor>,
     current: ResMut<Current>,
     mut dots: Query<&mut Transform>,
+    mut arcs: Query<&mut Arc>,
 ) {
     if current.dots.is_empty() {
         return;
@@ -169,9 +231,84 @@ pub fn handle_move_current_line(
     } else {
         warn!("Could not find currently sketched dot!");
     }
+
+    let Some(tangent) = current.tangent else {
+        return;
+    };
+    let Some(mut arc) = current
+        .curves
+        .first()
+        .and_then(|arc| arcs.get_mut(*arc).ok())
+    else {
+        return;
+    };
+    if let Ok(start) = dots.get(arc.start) {
+        arc.sweep = tangent_sweep(start.translation, cursor.position, tangent);
+    }
+}
+
+// Holding Alt turns the pending segment into an arc tangent to the previous one
+pub fn handle_tangent_arc_modifier(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut current: ResMut<Current>,
+    lines: Query<&Line>,
+    arcs: Query<&Arc>,
+) {
+    if current.tangent.is_none() {
+        return;
+    }
+    let is_tangent_arc = keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+
+    if is_tangent_arc && let Some(line_entity) = current.lines.first().copied() {
+        let Ok(line) = lines.get(line_entity) else {
+            return;
+        };
+        let arc = spawn_arc(&mut commands, line.start, line.end, 0.);
+        commands.entity(line_entity).despawn();
+        current.lines.clear();
+        current.curves.push(arc);
+    } else if !is_tangent_arc && let Some(arc_entity) = current.curves.first().copied() {
+        let Ok(arc) = arcs.get(arc_entity) else {
+            return;
+        };
+        let line = spawn_line(&mut commands, arc.start, arc.end);
+        commands.entity(arc_entity).despawn();
+        current.curves.clear();
+        current.lines.push(line);
+    }
+}
+
+// Remember the direction in which the pending segment arrives at its end dot
+pub fn update_chain_tangent(
+    mut current: ResMut<Current>,
+    lines: Query<&Line>,
+    arcs: Query<&Arc>,
+    dots: DotTransforms,
+) {
+    current.tangent = get_pending_end_tangent(&current, &lines, &arcs, &dots);
+}
+
+fn get_pending_end_tangent(
+    current: &Current,
+    lines: &Query<&Line>,
+    arcs: &Query<&Arc>,
+    dots: &DotTransforms,
+) -> Option<Vec3> {
+    let (start, end, sweep) =
+        if let Some(line) = current.lines.first().and_then(|l| lines.get(*l).ok()) {
+            (line.start, line.end, 0.)
+        } else {
+            let arc = current.curves.first().and_then(|a| arcs.get(*a).ok())?;
+            (arc.start, arc.end, arc.sweep)
+        };
+    let start = dots.get(start).ok()?.translation;
+    let end = dots.get(end).ok()?.translation;
+    let tangent = arc_end_tangent(start, end, sweep);
+    (tangent != Vec3::ZERO).then_some(tangent)
 }
 
-fn spawn_line(commands: &mut Commands, start: Entity, end: Entity) -> Entity {
+pub fn spawn_line(commands: &mut Commands, start: Entity, end: Entity) -> Entity {
     commands
         .spawn((
             Line { start, end },
@@ -182,31 +319,48 @@ fn spawn_line(commands: &mut Commands, start: Entity, end: Entity) -> Entity {
         .id()
 }
 
-pub fn display_lines(mut gizmos: Gizmos, lines: Query<&Line>, dots: Query<&Transform>) {
-    for line in lines.iter() {
+pub fn display_lines(
+    mut gizmos: Gizmos,
+    mut construction_gizmos: Gizmos<ConstructionGizmos>,
+    lines: Query<(&Line, Has<Construction>)>,
+    dots: Query<&Transform>,
+) {
+    for (line, is_construction) in lines.iter() {
         let Ok(start_position) = dots.get(line.start) else {
             continue;
         };
         let Ok(end_position) = dots.get(line.end) else {
             continue;
         };
-        gizmos.line(
-            start_position.translation,
-            end_position.translation,
-            color_from_hex(LINE),
-        );
+        if is_construction {
+            construction_gizmos.line(
+                start_position.translation,
+                end_position.translation,
+                color_from_hex(CONSTRUCTION),
+            );
+        } else {
+            gizmos.line(
+                start_position.translation,
+                end_position.translation,
+                color_from_hex(LINE),
+            );
+        }
     }
 }
 
 pub fn swap_line_end(
     next: Entity,
     current: &mut ResMut<Current>,
-    mut lines: Query<&mut Line>,
+    lines: &mut Query<&mut Line>,
+    arcs: &mut Query<&mut Arc>,
 ) -> Entity {
     let mut prev = *current.dots.last().unwrap();
-    if let Ok(mut line) = lines.get_mut(current.lines[0]) {
+    if let Some(Ok(mut line)) = current.lines.first().map(|line| lines.get_mut(*line)) {
         prev = line.end;
         line.end = next;
+    } else if let Some(Ok(mut arc)) = current.curves.first().map(|arc| arcs.get_mut(*arc)) {
+        prev = arc.end;
+        arc.end = next;
     }
     prev
 }
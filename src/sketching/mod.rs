@@ -1,5 +1,15 @@
+pub mod arc;
+pub mod circle;
+pub mod construction;
+pub mod coordinate;
+pub mod curve;
 pub mod dot;
+pub mod ellipse;
 pub mod line;
+pub mod polygon;
+pub mod rectangle;
 pub mod selection;
 pub mod size;
 pub mod sketch;
+pub mod slot;
+pub mod spline;
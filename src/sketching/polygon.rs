@@ -0,0 +1,195 @@
+use std::f32::consts::TAU;
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::{input_just_pressed, input_just_released};
+use bevy::input::mouse::AccumulatedMouseScroll;
+use bevy::prelude::*;
+
+use crate::cursor::{Cursor, Picking};
+use crate::schedule::ScheduleSet;
+
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::{finalize_lines, spawn_line};
+use super::sketch::{Current, SketchMode};
+
+pub const MIN_POLYGON_SIDES: u32 = 3;
+pub const MAX_POLYGON_SIDES: u32 = 64;
+// Shorter drags on screen are taken as a click that leaves the polygon in progress
+const DRAG_PIXELS: f32 = 4.;
+
+const SIDE_KEYS: [(KeyCode, u32); 7] = [
+    (KeyCode::Digit3, 3),
+    (KeyCode::Digit4, 4),
+    (KeyCode::Digit5, 5),
+    (KeyCode::Digit6, 6),
+    (KeyCode::Digit7, 7),
+    (KeyCode::Digit8, 8),
+    (KeyCode::Digit9, 9),
+];
+
+#[derive(Resource, Debug, PartialEq, Eq)]
+pub struct PolygonSides(pub u32);
+
+impl Default for PolygonSides {
+    fn default() -> Self {
+        PolygonSides(6)
+    }
+}
+
+pub struct PolygonPlugin;
+
+impl Plugin for PolygonPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PolygonSides::default()).add_systems(
+            Update,
+            (
+                (finalize_dots, finalize_lines, handle_sketch_polygon)
+                    .run_if(
+                        in_state(SketchMode::Polygon).and(input_just_pressed(MouseButton::Left)),
+                    )
+                    .chain(),
+                handle_polygon_sides.run_if(in_state(SketchMode::Polygon)),
+                handle_move_current_polygon.run_if(in_state(SketchMode::Polygon)),
+                (finalize_dots, finalize_lines, finish_polygon)
+                    .run_if(
+                        in_state(SketchMode::Polygon)
+                            .and(input_just_released(MouseButton::Left))
+                            .and(is_polygon_dragged),
+                    )
+                    .chain(),
+            )
+                .chain()
+                .in_set(ScheduleSet::EntityUpdates),
+        );
+    }
+}
+
+pub fn handle_sketch_polygon(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    picking: Res<Picking>,
+    sides: Res<PolygonSides>,
+    dots: Query<&Transform, With<Dot>>,
+) {
+    // Clicked without a drag, the preview follows the cursor until this second click
+    if !current.lines.is_empty() {
+        *current = Current::default();
+        return;
+    }
+
+    current.position[0] = dots
+        .get(picking.hovered)
+        .map_or(cursor.position, |transform| transform.translation);
+    spawn_current_polygon(&mut commands, &mut current, sides.0, cursor.position);
+}
+
+// Releasing away from the center after a drag sets the circumradius
+pub fn is_polygon_dragged(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) -> bool {
+    if current.lines.is_empty() {
+        return false;
+    }
+    let (camera, camera_transform) = *camera;
+    let to_screen = |position| camera.world_to_viewport(camera_transform, position).ok();
+    let (Some(center), Some(release)) =
+        (to_screen(current.position[0]), to_screen(cursor.position))
+    else {
+        return false;
+    };
+    center.distance(release) > DRAG_PIXELS
+}
+
+pub fn finish_polygon(mut current: ResMut<Current>) {
+    *current = Current::default();
+}
+
+// Number keys pick the side count directly, the scroll wheel steps through it
+pub fn handle_polygon_sides(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    scroll: Res<AccumulatedMouseScroll>,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    mut sides: ResMut<PolygonSides>,
+) {
+    let mut next_sides = sides.0;
+    for (key, key_sides) in SIDE_KEYS {
+        if keyboard.just_pressed(key) {
+            next_sides = key_sides;
+        }
+    }
+    if scroll.delta.y > 0. {
+        next_sides += 1;
+    } else if scroll.delta.y < 0. {
+        next_sides -= 1;
+    }
+    next_sides = next_sides.clamp(MIN_POLYGON_SIDES, MAX_POLYGON_SIDES);
+    if next_sides == sides.0 {
+        return;
+    }
+    sides.0 = next_sides;
+
+    // Rebuild a live preview with the new side count
+    if current.lines.is_empty() {
+        return;
+    }
+    let current = current.as_mut();
+    for entity in current.lines.drain(..).chain(current.dots.drain(..)) {
+        commands.entity(entity).despawn();
+    }
+    spawn_current_polygon(&mut commands, current, sides.0, cursor.position);
+}
+
+pub fn handle_move_current_polygon(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    if current.lines.is_empty() {
+        return;
+    }
+    let vertices = polygon_vertices(
+        current.position[0],
+        cursor.position,
+        current.dots.len() as u32,
+    );
+    for (dot, vertex) in current.dots.iter().zip(vertices) {
+        if let Ok(mut transform) = dots.get_mut(*dot) {
+            transform.translation = vertex;
+        } else {
+            warn!("Could not find currently sketched dot!");
+        }
+    }
+}
+
+// Closed ring of temporary dots and lines around the center in `current`
+fn spawn_current_polygon(commands: &mut Commands, current: &mut Current, sides: u32, corner: Vec3) {
+    let vertices = polygon_vertices(current.position[0], corner, sides);
+    let polygon_dots: Vec<Entity> = vertices
+        .iter()
+        .map(|vertex| spawn_temporary_dot(commands, *vertex))
+        .collect();
+    for i in 0..polygon_dots.len() {
+        let start = polygon_dots[i];
+        let end = polygon_dots[(i + 1) % polygon_dots.len()];
+        current.lines.push(spawn_line(commands, start, end));
+    }
+    current.dots = polygon_dots;
+}
+
+// Vertices of a regular polygon around a center, with the first vertex at the corner
+pub fn polygon_vertices(center: Vec3, corner: Vec3, sides: u32) -> Vec<Vec3> {
+    let offset = corner - center;
+    let radius = offset.length();
+    let rotation = offset.y.atan2(offset.x);
+    (0..sides)
+        .map(|i| {
+            let angle = rotation + TAU * i as f32 / sides as f32;
+            center + radius * Vec3::new(angle.cos(), angle.sin(), 0.)
+        })
+        .collect()
+}
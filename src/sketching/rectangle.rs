@@ -0,0 +1,123 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::cursor::{Cursor, Picking};
+use crate::schedule::ScheduleSet;
+
+use super::coordinate::is_placing_point;
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::{finalize_lines, spawn_line};
+use super::sketch::{CYCLE_KIND_KEY, Current, SketchMode};
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RectangleKind {
+    #[default]
+    Corner,
+    Center,
+}
+
+pub struct RectanglePlugin;
+
+impl Plugin for RectanglePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RectangleKind::default()).add_systems(
+            Update,
+            (
+                cycle_rectangle_kind.run_if(
+                    in_state(SketchMode::Rectangle).and(input_just_pressed(CYCLE_KIND_KEY)),
+                ),
+                (finalize_dots, finalize_lines, handle_sketch_rectangle)
+                    .run_if(in_state(SketchMode::Rectangle).and(is_placing_point))
+                    .chain(),
+                handle_move_current_rectangle.run_if(in_state(SketchMode::Rectangle)),
+            )
+                .chain()
+                .in_set(ScheduleSet::EntityUpdates),
+        );
+    }
+}
+
+fn cycle_rectangle_kind(mut kind: ResMut<RectangleKind>) {
+    *kind = match *kind {
+        RectangleKind::Corner => RectangleKind::Center,
+        RectangleKind::Center => RectangleKind::Corner,
+    };
+}
+
+pub fn handle_sketch_rectangle(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    picking: Res<Picking>,
+    kind: Res<RectangleKind>,
+    dots: Query<&Transform, With<Dot>>,
+) {
+    // Second click: the previewed rectangle was finalized, so just let go of it
+    if !current.dots.is_empty() {
+        *current = Current::default();
+        return;
+    }
+
+    let mut anchor = cursor.position;
+    let mut first_dot = Entity::PLACEHOLDER;
+    // Start from an existing dot so the rectangle connects to it
+    if *kind == RectangleKind::Corner
+        && let Ok(transform) = dots.get(picking.hovered)
+    {
+        anchor = transform.translation;
+        first_dot = picking.hovered;
+    }
+
+    let corners = rectangle_corners(*kind, anchor, cursor.position);
+    let mut rectangle_dots = [Entity::PLACEHOLDER; 4];
+    for (i, corner) in corners.iter().enumerate() {
+        rectangle_dots[i] = if i == 0 && first_dot != Entity::PLACEHOLDER {
+            first_dot
+        } else {
+            spawn_temporary_dot(&mut commands, *corner)
+        };
+    }
+
+    current.position[0] = anchor;
+    for i in 0..rectangle_dots.len() {
+        let start = rectangle_dots[i];
+        let end = rectangle_dots[(i + 1) % rectangle_dots.len()];
+        let line = spawn_line(&mut commands, start, end);
+        current.lines.push(line);
+    }
+    // A reused dot is already final and must not be despawned on cancel
+    current
+        .dots
+        .extend(rectangle_dots.iter().filter(|dot| **dot != first_dot));
+}
+
+pub fn handle_move_current_rectangle(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    kind: Res<RectangleKind>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    if current.lines.len() != 4 {
+        return;
+    }
+    let corners = rectangle_corners(*kind, current.position[0], cursor.position);
+    // A reused anchor dot is not in `current`, so only the trailing corners move
+    let moved_corners = &corners[corners.len() - current.dots.len()..];
+    for (dot, corner) in current.dots.iter().zip(moved_corners) {
+        if let Ok(mut transform) = dots.get_mut(*dot) {
+            transform.translation = *corner;
+        } else {
+            warn!("Could not find currently sketched dot!");
+        }
+    }
+}
+
+// Corners in winding order, starting at the anchor for corner rectangles
+pub fn rectangle_corners(kind: RectangleKind, anchor: Vec3, cursor: Vec3) -> [Vec3; 4] {
+    let (a, c) = match kind {
+        RectangleKind::Corner => (anchor, cursor),
+        RectangleKind::Center => (2. * anchor - cursor, cursor),
+    };
+    [a, Vec3::new(c.x, a.y, a.z), c, Vec3::new(a.x, c.y, a.z)]
+}
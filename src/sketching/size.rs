@@ -1,3 +1,4 @@
 pub const LINE_WIDTH: f32 = 2.5;
 pub const LINE_MESH_WIDTH: f32 = 0.1;
 pub const DOT_MESH_RADIUS: f32 = 0.025;
+pub const CURVE_MESH_WIDTH: f32 = 0.02;
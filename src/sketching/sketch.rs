@@ -1,17 +1,24 @@
 use bevy::input::common_conditions::input_pressed;
 use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 
+use crate::constraints::solver::ConstraintSolver;
 use crate::cursor::{Cursor, is_cursor_moving};
 use crate::schedule::ScheduleSet;
 
+use super::arc::{Arc, ArcPlugin};
+use super::circle::{Circle, CirclePlugin};
+use super::construction::ConstructionPlugin;
+use super::coordinate::CoordinatePlugin;
+use super::curve::{display_curves, mark_moving_curves, update_curve_meshes};
 use super::dot::mark_moving_dots;
+use super::ellipse::{Ellipse, EllipsePlugin, EllipticalArc};
 use super::line::{display_lines, mark_moving_lines, update_line_mesh_transforms};
+use super::polygon::PolygonPlugin;
+use super::rectangle::RectanglePlugin;
+use super::slot::SlotPlugin;
+use super::spline::{Spline, SplinePlugin};
 use super::{dot::DotPlugin, line::LinePlugin, size::LINE_WIDTH};
 
-// use super::arc::{ArcPlugin, handle_sketch_arc};
-// use super::circle::{CirclePlugin, handle_sketch_circle};
-// use super::rectangle::{RectanglePlugin, handle_sketch_rectangle};
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, States, Default, Reflect)]
 pub enum SketchMode {
     #[default]
@@ -21,20 +28,29 @@ pub enum SketchMode {
     Rectangle,
     Circle,
     Arc,
+    Spline,
+    Ellipse,
+    Polygon,
+    Slot,
 }
 
 #[derive(Component, Default)]
 #[component(storage = "SparseSet")]
 pub struct Moving;
 
-// pub const DEFAULT_RESOLUTION: u32 = 64;
+pub const DEFAULT_RESOLUTION: u32 = 64;
 pub const DEFAULT_POS: Vec3 = Vec3::splat(f32::MIN);
+// Steps through the variants of the current tool, apart from the keys that switch tools
+pub const CYCLE_KIND_KEY: KeyCode = KeyCode::Tab;
 
 #[derive(Resource, Debug, PartialEq)]
 pub struct Current {
     pub position: [Vec3; 3],
     pub dots: Vec<Entity>,
     pub lines: Vec<Entity>,
+    pub curves: Vec<Entity>,
+    pub tangent: Option<Vec3>,
+    pub chain_start: Option<Entity>,
 }
 
 #[derive(Resource, Debug, Default, PartialEq)]
@@ -48,6 +64,9 @@ impl Default for Current {
             position: [DEFAULT_POS, DEFAULT_POS, DEFAULT_POS],
             dots: Vec::new(),
             lines: Vec::new(),
+            curves: Vec::new(),
+            tangent: None,
+            chain_start: None,
         }
     }
 }
@@ -61,6 +80,15 @@ impl Plugin for SketchPlugin {
             .insert_resource(Checked::default())
             .add_plugins(DotPlugin)
             .add_plugins(LinePlugin)
+            .add_plugins(RectanglePlugin)
+            .add_plugins(CirclePlugin)
+            .add_plugins(ArcPlugin)
+            .add_plugins(SplinePlugin)
+            .add_plugins(EllipsePlugin)
+            .add_plugins(PolygonPlugin)
+            .add_plugins(SlotPlugin)
+            .add_plugins(ConstructionPlugin)
+            .add_plugins(CoordinatePlugin)
             .add_systems(Startup, sketch_setup)
             .add_systems(
                 Update,
@@ -70,13 +98,29 @@ impl Plugin for SketchPlugin {
                     (
                         mark_moving_dots,
                         mark_moving_lines,
+                        mark_moving_curves::<Circle>,
+                        mark_moving_curves::<Arc>,
+                        mark_moving_curves::<Spline>,
+                        mark_moving_curves::<Ellipse>,
+                        mark_moving_curves::<EllipticalArc>,
                         update_moving_transforms,
                     )
                         .run_if(is_dragging())
                         .chain(),
-                    update_line_mesh_transforms.run_if(is_cursor_moving),
+                    update_line_mesh_transforms
+                        .run_if(is_cursor_moving.or(resource_changed::<ConstraintSolver>)),
+                    update_curve_meshes::<Circle>,
+                    update_curve_meshes::<Arc>,
+                    update_curve_meshes::<Spline>,
+                    update_curve_meshes::<Ellipse>,
+                    update_curve_meshes::<EllipticalArc>,
                     remove_moving.run_if(not(is_cursor_moving)),
                     display_lines,
+                    display_curves::<Circle>,
+                    display_curves::<Arc>,
+                    display_curves::<Spline>,
+                    display_curves::<Ellipse>,
+                    display_curves::<EllipticalArc>,
                 )
                     .chain()
                     .in_set(ScheduleSet::EntityUpdates),
@@ -95,6 +139,10 @@ fn change_sketch_mode(
     mut state: ResMut<NextState<SketchMode>>,
     current: ResMut<Current>,
 ) {
+    // Alt combinations are constraint shortcuts
+    if keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
     if keyboard.just_pressed(KeyCode::Escape) {
         reset_current(commands, current);
         state.set(SketchMode::None);
@@ -104,6 +152,27 @@ fn change_sketch_mode(
     } else if keyboard.just_pressed(KeyCode::KeyS) {
         reset_current(commands, current);
         state.set(SketchMode::Line);
+    } else if keyboard.just_pressed(KeyCode::KeyR) {
+        reset_current(commands, current);
+        state.set(SketchMode::Rectangle);
+    } else if keyboard.just_pressed(KeyCode::KeyC) {
+        reset_current(commands, current);
+        state.set(SketchMode::Circle);
+    } else if keyboard.just_pressed(KeyCode::KeyA) {
+        reset_current(commands, current);
+        state.set(SketchMode::Arc);
+    } else if keyboard.just_pressed(KeyCode::KeyB) {
+        reset_current(commands, current);
+        state.set(SketchMode::Spline);
+    } else if keyboard.just_pressed(KeyCode::KeyE) {
+        reset_current(commands, current);
+        state.set(SketchMode::Ellipse);
+    } else if keyboard.just_pressed(KeyCode::KeyP) {
+        reset_current(commands, current);
+        state.set(SketchMode::Polygon);
+    } else if keyboard.just_pressed(KeyCode::KeyO) {
+        reset_current(commands, current);
+        state.set(SketchMode::Slot);
     }
 }
 
@@ -111,6 +180,9 @@ pub fn reset_current(mut commands: Commands, mut current: ResMut<Current>) {
     for entity in &current.lines {
         commands.entity(*entity).despawn();
     }
+    for entity in &current.curves {
+        commands.entity(*entity).despawn();
+    }
     for entity in &current.dots {
         commands.entity(*entity).despawn();
     }
@@ -123,6 +195,7 @@ pub fn remove_moving(mut commands: Commands, query: Query<Entity, With<Moving>>)
     }
 }
 
+// Constraints then pull the rest of the sketch along, see solve_constraints
 pub fn update_moving_transforms(
     cursor: Res<Cursor>,
     mut query: Query<&mut Transform, With<Moving>>,
@@ -0,0 +1,198 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::cursor::Cursor;
+use crate::schedule::ScheduleSet;
+
+use super::arc::{Arc, ccw_angle, finalize_arcs, spawn_arc};
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::{finalize_lines, spawn_line};
+use super::sketch::{CYCLE_KIND_KEY, Current, DEFAULT_POS, SketchMode};
+
+// Smallest half width, so a slot never collapses onto its centerline
+const MIN_HALF_WIDTH: f32 = 0.01;
+
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SlotKind {
+    #[default]
+    Straight,
+    Arc,
+}
+
+// Outline dots in counterclockwise order with the sweeps of the segments leaving them
+struct SlotOutline {
+    dots: [Vec3; 4],
+    sweeps: [f32; 4],
+}
+
+pub struct SlotPlugin;
+
+impl Plugin for SlotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SlotKind::default()).add_systems(
+            Update,
+            (
+                cycle_slot_kind
+                    .run_if(in_state(SketchMode::Slot).and(input_just_pressed(CYCLE_KIND_KEY))),
+                (
+                    (finalize_dots, finalize_lines, finalize_arcs).run_if(is_current_slot_complete),
+                    handle_sketch_slot,
+                )
+                    .run_if(in_state(SketchMode::Slot).and(input_just_pressed(MouseButton::Left)))
+                    .chain(),
+                handle_move_current_slot.run_if(in_state(SketchMode::Slot)),
+            )
+                .chain()
+                .in_set(ScheduleSet::EntityUpdates),
+        );
+    }
+}
+
+fn cycle_slot_kind(mut kind: ResMut<SlotKind>) {
+    *kind = match *kind {
+        SlotKind::Straight => SlotKind::Arc,
+        SlotKind::Arc => SlotKind::Straight,
+    };
+}
+
+// Straight slots take both centers and the width.
+// Arc slots take the centerline's center, start and end, then the width.
+pub fn handle_sketch_slot(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    kind: Res<SlotKind>,
+) {
+    // Final click: the previewed slot was finalized, so just let go of it
+    if is_slot_complete(&current, *kind) {
+        *current = Current::default();
+        return;
+    }
+
+    if current.position[0] == DEFAULT_POS {
+        current.position[0] = cursor.position;
+        let outline = slot_outline(&current, *kind, cursor.position);
+        let slot_dots = outline
+            .dots
+            .map(|dot| spawn_temporary_dot(&mut commands, dot));
+        for i in 0..slot_dots.len() {
+            let start = slot_dots[i];
+            let end = slot_dots[(i + 1) % slot_dots.len()];
+            if is_line_segment(*kind, i) {
+                current.lines.push(spawn_line(&mut commands, start, end));
+            } else {
+                let sweep = outline.sweeps[i];
+                current
+                    .curves
+                    .push(spawn_arc(&mut commands, start, end, sweep));
+            }
+        }
+        current.dots.extend(slot_dots);
+    } else if current.position[1] == DEFAULT_POS {
+        current.position[1] = cursor.position;
+    } else {
+        current.position[2] = cursor.position;
+    }
+}
+
+pub fn is_current_slot_complete(current: Res<Current>, kind: Res<SlotKind>) -> bool {
+    is_slot_complete(&current, *kind)
+}
+
+// Whether the next click sets the width of the current slot
+fn is_slot_complete(current: &Current, kind: SlotKind) -> bool {
+    match kind {
+        SlotKind::Straight => current.position[1] != DEFAULT_POS,
+        SlotKind::Arc => current.position[2] != DEFAULT_POS,
+    }
+}
+
+pub fn handle_move_current_slot(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    kind: Res<SlotKind>,
+    mut arcs: Query<&mut Arc>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    if current.dots.len() != 4 {
+        return;
+    }
+    let outline = slot_outline(&current, *kind, cursor.position);
+    for (dot, position) in current.dots.iter().zip(outline.dots) {
+        if let Ok(mut transform) = dots.get_mut(*dot) {
+            transform.translation = position;
+        } else {
+            warn!("Could not find currently sketched dot!");
+        }
+    }
+    let arc_sweeps = (0..outline.sweeps.len())
+        .filter(|i| !is_line_segment(*kind, *i))
+        .map(|i| outline.sweeps[i]);
+    for (arc, sweep) in current.curves.iter().zip(arc_sweeps) {
+        if let Ok(mut arc) = arcs.get_mut(*arc) {
+            arc.sweep = sweep;
+        }
+    }
+}
+
+// Straight slots have lines along their sides and arcs only at their ends
+fn is_line_segment(kind: SlotKind, segment: usize) -> bool {
+    kind == SlotKind::Straight && segment.is_multiple_of(2)
+}
+
+// Outline for the clicks placed so far, with the cursor standing in for the next one
+fn slot_outline(current: &Current, kind: SlotKind, cursor: Vec3) -> SlotOutline {
+    let [first, second, third] = current.position;
+    match kind {
+        SlotKind::Straight if second == DEFAULT_POS => {
+            straight_slot_outline(first, cursor, first.distance(cursor) / 4.)
+        }
+        SlotKind::Straight => {
+            let direction = (second - first).normalize_or_zero();
+            let normal = Vec3::new(-direction.y, direction.x, 0.);
+            straight_slot_outline(first, second, (cursor - first).dot(normal).abs())
+        }
+        SlotKind::Arc if second == DEFAULT_POS => {
+            arc_slot_outline(first, cursor, FRAC_PI_2, first.distance(cursor) / 4.)
+        }
+        SlotKind::Arc if third == DEFAULT_POS => {
+            let sweep = ccw_angle(second - first, cursor - first);
+            arc_slot_outline(first, second, sweep, first.distance(second) / 4.)
+        }
+        SlotKind::Arc => {
+            let sweep = ccw_angle(second - first, third - first);
+            let half_width = (first.distance(cursor) - first.distance(second)).abs();
+            arc_slot_outline(first, second, sweep, half_width)
+        }
+    }
+}
+
+fn straight_slot_outline(start: Vec3, end: Vec3, half_width: f32) -> SlotOutline {
+    let direction = (end - start).normalize_or_zero();
+    let offset = Vec3::new(-direction.y, direction.x, 0.) * half_width.max(MIN_HALF_WIDTH);
+    SlotOutline {
+        dots: [start - offset, end - offset, end + offset, start + offset],
+        sweeps: [0., PI, 0., PI],
+    }
+}
+
+// Slot along the arc around a center that starts at a point and sweeps counterclockwise
+fn arc_slot_outline(center: Vec3, start: Vec3, sweep: f32, half_width: f32) -> SlotOutline {
+    let radius = center.distance(start);
+    let half_width = half_width.clamp(MIN_HALF_WIDTH.min(radius / 2.), radius * 0.99);
+    let start_direction = (start - center).normalize_or_zero();
+    let end_direction = Quat::from_rotation_z(sweep) * start_direction;
+    let end = center + end_direction * radius;
+    SlotOutline {
+        dots: [
+            start + start_direction * half_width,
+            end + end_direction * half_width,
+            end - end_direction * half_width,
+            start - start_direction * half_width,
+        ],
+        sweeps: [sweep, PI, -sweep, PI],
+    }
+}
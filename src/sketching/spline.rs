@@ -0,0 +1,286 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::assets::materials::{UIMaterialProvider, UIMaterials};
+use crate::cursor::{Cursor, Picking};
+use crate::reload::{ReloadLevel, Reloadable};
+use crate::schedule::ScheduleSet;
+
+use super::curve::{DotTransforms, SketchCurve, delete_dependent_curves, finalize_curve};
+use super::dot::{Dot, finalize_dots, spawn_temporary_dot};
+use super::line::delete_selected_entities;
+use super::selection::Selected;
+use super::sketch::{Current, DEFAULT_RESOLUTION, SketchMode, reset_current};
+
+pub const MIN_RESOLUTION: u32 = 4;
+pub const MAX_RESOLUTION: u32 = 256;
+
+// Clamped cubic B-spline through its first and last control dots
+#[derive(Component, Debug, PartialEq)]
+pub struct Spline {
+    pub controls: Vec<Entity>,
+    // Samples along the whole curve
+    pub resolution: u32,
+}
+
+impl UIMaterialProvider for Spline {
+    fn get_material(ui_materials: &UIMaterials) -> Handle<StandardMaterial> {
+        ui_materials.line.clone()
+    }
+}
+
+impl SketchCurve for Spline {
+    fn dots(&self) -> Vec<Entity> {
+        self.controls.clone()
+    }
+
+    fn points(&self, dots: &DotTransforms) -> Option<Vec<Vec3>> {
+        let controls = self
+            .controls
+            .iter()
+            .map(|dot| dots.get(*dot).ok().map(|transform| transform.translation))
+            .collect::<Option<Vec<Vec3>>>()?;
+        Some(spline_points(&controls, self.resolution))
+    }
+}
+
+pub struct SplinePlugin;
+
+impl Plugin for SplinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                handle_sketch_spline.run_if(
+                    in_state(SketchMode::Spline).and(input_just_pressed(MouseButton::Left)),
+                ),
+                (
+                    remove_spline_cursor_dot,
+                    finalize_dots,
+                    finalize_splines,
+                    end_spline,
+                )
+                    .run_if(in_state(SketchMode::Spline).and(input_just_pressed(KeyCode::Enter)))
+                    .chain(),
+                handle_move_current_spline.run_if(in_state(SketchMode::Spline)),
+                adjust_spline_resolution.run_if(
+                    input_just_pressed(KeyCode::Equal).or(input_just_pressed(KeyCode::Minus)),
+                ),
+                display_spline_controls,
+            )
+                .chain()
+                .in_set(ScheduleSet::EntityUpdates),
+        )
+        .add_systems(
+            Update,
+            delete_dependent_curves::<Spline>
+                .after(delete_selected_entities)
+                .run_if(input_just_pressed(KeyCode::KeyX))
+                .in_set(ScheduleSet::DespawnEntities),
+        );
+    }
+}
+
+// Every click pins the control dot that follows the cursor and starts a new one
+pub fn handle_sketch_spline(
+    mut commands: Commands,
+    cursor: Res<Cursor>,
+    mut current: ResMut<Current>,
+    picking: Res<Picking>,
+    mut splines: Query<&mut Spline>,
+    dots: Query<&Dot>,
+) {
+    let is_hovering_dot = dots.contains(picking.hovered);
+    let cursor_dot = spawn_temporary_dot(&mut commands, cursor.position);
+    current.dots.push(cursor_dot);
+
+    let Some(mut spline) = current
+        .curves
+        .first()
+        .and_then(|spline| splines.get_mut(*spline).ok())
+    else {
+        let first = if is_hovering_dot {
+            picking.hovered
+        } else {
+            let dot = spawn_temporary_dot(&mut commands, cursor.position);
+            current.dots.push(dot);
+            dot
+        };
+        let spline = spawn_spline(&mut commands, vec![first, cursor_dot]);
+        current.curves.push(spline);
+        return;
+    };
+
+    // Connect to the hovered dot instead of the one that followed the cursor
+    if is_hovering_dot && !spline.controls.contains(&picking.hovered) {
+        if let Some(pinned) = spline.controls.pop() {
+            current.dots.retain(|dot| *dot != pinned);
+            commands.entity(pinned).despawn();
+        }
+        spline.controls.push(picking.hovered);
+    }
+    spline.controls.push(cursor_dot);
+}
+
+pub fn handle_move_current_spline(
+    cursor: Res<Cursor>,
+    current: Res<Current>,
+    splines: Query<&Spline>,
+    mut dots: Query<&mut Transform, With<Dot>>,
+) {
+    let Some(spline) = current
+        .curves
+        .first()
+        .and_then(|spline| splines.get(*spline).ok())
+    else {
+        return;
+    };
+    let Some(cursor_dot) = spline.controls.last() else {
+        return;
+    };
+    if let Ok(mut transform) = dots.get_mut(*cursor_dot) {
+        transform.translation = cursor.position;
+    } else {
+        warn!("Could not find currently sketched dot!");
+    }
+}
+
+// Drop the control dot that follows the cursor so the spline ends at the last click
+pub fn remove_spline_cursor_dot(
+    mut commands: Commands,
+    mut current: ResMut<Current>,
+    mut splines: Query<&mut Spline>,
+) {
+    let Some(mut spline) = current
+        .curves
+        .first()
+        .and_then(|spline| splines.get_mut(*spline).ok())
+    else {
+        return;
+    };
+    if let Some(cursor_dot) = spline.controls.pop() {
+        current.dots.retain(|dot| *dot != cursor_dot);
+        commands.entity(cursor_dot).despawn();
+    }
+}
+
+pub fn finalize_splines(
+    mut commands: Commands,
+    current: Res<Current>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    ui_materials: Res<UIMaterials>,
+    splines: Query<&Spline>,
+    dots: DotTransforms,
+) {
+    for entity in &current.curves {
+        let Ok(spline) = splines.get(*entity) else {
+            continue;
+        };
+        finalize_curve(
+            &mut commands,
+            &mut meshes,
+            &ui_materials,
+            *entity,
+            spline,
+            &dots,
+        );
+    }
+}
+
+// Keep the finished spline, or discard it if too few dots were placed
+pub fn end_spline(commands: Commands, mut current: ResMut<Current>, splines: Query<&Spline>) {
+    let is_too_short = current
+        .curves
+        .first()
+        .and_then(|spline| splines.get(*spline).ok())
+        .is_none_or(|spline| spline.controls.len() < 2);
+    if is_too_short {
+        reset_current(commands, current);
+    } else {
+        *current = Current::default();
+    }
+}
+
+pub fn adjust_spline_resolution(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut splines: Query<&mut Spline, With<Selected>>,
+) {
+    for mut spline in splines.iter_mut() {
+        spline.resolution = if keyboard.just_pressed(KeyCode::Equal) {
+            (spline.resolution * 2).min(MAX_RESOLUTION)
+        } else {
+            (spline.resolution / 2).max(MIN_RESOLUTION)
+        };
+    }
+}
+
+pub fn display_spline_controls(mut gizmos: Gizmos, splines: Query<&Spline>, dots: DotTransforms) {
+    for spline in splines.iter() {
+        let controls = spline
+            .controls
+            .iter()
+            .filter_map(|dot| dots.get(*dot).ok().map(|transform| transform.translation));
+        gizmos.linestrip(controls, color_from_hex(COOL_BLUE));
+    }
+}
+
+pub fn spawn_spline(commands: &mut Commands, controls: Vec<Entity>) -> Entity {
+    commands
+        .spawn((
+            Spline {
+                controls,
+                resolution: DEFAULT_RESOLUTION,
+            },
+            Reloadable {
+                level: ReloadLevel::Hard,
+            },
+        ))
+        .id()
+}
+
+pub fn spline_points(controls: &[Vec3], resolution: u32) -> Vec<Vec3> {
+    if controls.len() < 3 {
+        return controls.to_vec();
+    }
+    let degree = 3.min(controls.len() - 1);
+    let spans = controls.len() - degree;
+    let knots = clamped_knots(controls.len(), degree);
+    let samples = resolution.max(spans as u32);
+    (0..=samples)
+        .map(|i| {
+            let t = spans as f32 * i as f32 / samples as f32;
+            de_boor(controls, &knots, degree, t)
+        })
+        .collect()
+}
+
+// Knots that repeat at both ends so the curve starts and ends on its outer controls
+fn clamped_knots(count: usize, degree: usize) -> Vec<f32> {
+    let spans = count - degree;
+    (0..count + degree + 1)
+        .map(|i| i.saturating_sub(degree).min(spans) as f32)
+        .collect()
+}
+
+fn de_boor(controls: &[Vec3], knots: &[f32], degree: usize, t: f32) -> Vec3 {
+    let span = (degree..controls.len())
+        .rev()
+        .find(|k| knots[*k] <= t)
+        .unwrap_or(degree);
+    let mut d: Vec<Vec3> = (0..=degree).map(|j| controls[j + span - degree]).collect();
+    for r in 1..=degree {
+        for j in (r..=degree).rev() {
+            let left = knots[j + span - degree];
+            let right = knots[j + 1 + span - r];
+            let alpha = if right > left {
+                (t - left) / (right - left)
+            } else {
+                0.
+            };
+            d[j] = d[j - 1].lerp(d[j], alpha);
+        }
+    }
+    d[degree]
+}
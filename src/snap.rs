@@ -0,0 +1,385 @@
+use bevy::ecs::schedule::IntoScheduleConfigs;
+use bevy::ecs::system::SystemParam;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::prelude::*;
+
+use crate::assets::colors::*;
+use crate::cursor::{Cursor, Picking, hover_entity, mark_hovered_changing_material};
+use crate::schedule::ScheduleSet;
+use crate::sketching::arc::{Arc, arc_center};
+use crate::sketching::circle::Circle;
+use crate::sketching::dot::Dot;
+use crate::sketching::line::Line;
+use crate::sketching::sketch::{Current, SketchMode};
+
+// How close to the mouse on screen a snap point has to be
+const SNAP_PIXELS: f32 = 12.;
+const SNAP_MARKER_PIXELS: f32 = 7.;
+// Angles in degrees that Shift can hold a line to
+const ANGLE_INCREMENTS: [f32; 3] = [15., 45., 90.];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SnapKind {
+    // In order of preference when several are equally close
+    Endpoint,
+    Center,
+    Midpoint,
+    Intersection,
+    Perpendicular,
+    Tangent,
+}
+
+// Point the cursor snapped to this frame
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Snap {
+    pub kind: Option<SnapKind>,
+    pub position: Vec3,
+    // Dot or curve the point belongs to, if any
+    pub target: Entity,
+}
+
+impl Default for Snap {
+    fn default() -> Self {
+        Snap {
+            kind: None,
+            position: Vec3::ZERO,
+            target: Entity::PLACEHOLDER,
+        }
+    }
+}
+
+// Shift holds the line being drawn to multiples of `increment`, and ortho to horizontal or vertical
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct AngleSnap {
+    pub increment: f32,
+    pub is_ortho: bool,
+}
+
+impl Default for AngleSnap {
+    fn default() -> Self {
+        AngleSnap {
+            increment: ANGLE_INCREMENTS[0],
+            is_ortho: false,
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct SnapGeometry<'w, 's> {
+    dots: Query<'w, 's, (Entity, &'static Transform), With<Dot>>,
+    lines: Query<'w, 's, (Entity, &'static Line)>,
+    circles: Query<'w, 's, (Entity, &'static Circle)>,
+    arcs: Query<'w, 's, (Entity, &'static Arc)>,
+}
+
+impl SnapGeometry<'_, '_> {
+    fn position(&self, dot: Entity) -> Option<Vec3> {
+        self.dots
+            .get(dot)
+            .ok()
+            .map(|(_, transform)| transform.translation)
+    }
+
+    // Snap points of everything but the geometry being sketched, with perpendicular
+    // and tangent points taken from where the current segment starts
+    fn candidates(&self, current: &Current) -> Vec<Snap> {
+        let mut candidates = Vec::new();
+        let mut add = |kind, position: Vec3, target| {
+            candidates.push(Snap {
+                kind: Some(kind),
+                position,
+                target,
+            })
+        };
+        let from = current
+            .lines
+            .first()
+            .and_then(|line| self.lines.get(*line).ok())
+            .and_then(|(_, line)| self.position(line.start));
+
+        let circles: Vec<(Entity, &Circle)> = self
+            .circles
+            .iter()
+            .filter(|(entity, _)| !current.curves.contains(entity))
+            .collect();
+        for (entity, transform) in self.dots.iter() {
+            let is_center = circles.iter().any(|(_, circle)| circle.center == entity);
+            if !current.dots.contains(&entity) && !is_center {
+                add(SnapKind::Endpoint, transform.translation, entity);
+            }
+        }
+        for (entity, circle) in circles {
+            let Some(center) = self.position(circle.center) else {
+                continue;
+            };
+            add(SnapKind::Center, center, circle.center);
+            for point in from.map_or(Vec::new(), |from| {
+                tangent_points(from, center, circle.radius)
+            }) {
+                add(SnapKind::Tangent, point, entity);
+            }
+        }
+        for (entity, arc) in self.arcs.iter() {
+            if current.curves.contains(&entity) {
+                continue;
+            }
+            let Some((center, radius)) = self
+                .position(arc.start)
+                .zip(self.position(arc.end))
+                .and_then(|(start, end)| arc_center(start, end, arc.sweep))
+            else {
+                continue;
+            };
+            add(SnapKind::Center, center, Entity::PLACEHOLDER);
+            for point in from.map_or(Vec::new(), |from| tangent_points(from, center, radius)) {
+                add(SnapKind::Tangent, point, entity);
+            }
+        }
+
+        let segments: Vec<(Entity, Vec3, Vec3)> = self
+            .lines
+            .iter()
+            .filter(|(entity, _)| !current.lines.contains(entity))
+            .filter_map(|(entity, line)| {
+                Some((entity, self.position(line.start)?, self.position(line.end)?))
+            })
+            .collect();
+        for (i, (entity, start, end)) in segments.iter().enumerate() {
+            add(SnapKind::Midpoint, (*start + *end) / 2., *entity);
+            if let Some(foot) = from.and_then(|from| perpendicular_foot(from, *start, *end)) {
+                add(SnapKind::Perpendicular, foot, *entity);
+            }
+            for (_, other_start, other_end) in &segments[i + 1..] {
+                if let Some(point) = intersection(*start, *end, *other_start, *other_end) {
+                    add(SnapKind::Intersection, point, Entity::PLACEHOLDER);
+                }
+            }
+        }
+        candidates
+    }
+}
+
+pub struct SnapPlugin;
+
+impl Plugin for SnapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Snap::default())
+            .insert_resource(AngleSnap::default())
+            .add_systems(OnEnter(SketchMode::None), reset_snap)
+            .add_systems(
+                Update,
+                (
+                    handle_angle_snap_keys.run_if(
+                        input_just_pressed(KeyCode::F7).or(input_just_pressed(KeyCode::F8)),
+                    ),
+                    snap_cursor.run_if(not(in_state(SketchMode::None))),
+                    snap_line_angle.run_if(in_state(SketchMode::Line)),
+                )
+                    .chain()
+                    .after(hover_entity)
+                    .before(mark_hovered_changing_material)
+                    .in_set(ScheduleSet::UserInput),
+            )
+            .add_systems(
+                Update,
+                display_snap_marker.in_set(ScheduleSet::EntityUpdates),
+            );
+    }
+}
+
+pub fn reset_snap(mut snap: ResMut<Snap>) {
+    *snap = Snap::default();
+}
+
+// Snapping to a dot or curve also hovers it, so tools connect to it as if it had been hovered exactly
+pub fn snap_cursor(
+    mut cursor: ResMut<Cursor>,
+    mut picking: ResMut<Picking>,
+    mut snap: ResMut<Snap>,
+    current: Res<Current>,
+    geometry: SnapGeometry,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window>,
+) {
+    *snap = Snap::default();
+    let Some(mouse) = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+    let (camera, camera_transform) = *camera;
+    let closest = geometry
+        .candidates(&current)
+        .into_iter()
+        .filter_map(|candidate| {
+            let screen = camera
+                .world_to_viewport(camera_transform, candidate.position)
+                .ok()?;
+            let distance = screen.distance(mouse);
+            (distance <= SNAP_PIXELS).then_some((distance.round() as u32, candidate))
+        })
+        .min_by_key(|(distance, candidate)| (*distance, candidate.kind));
+    let Some((_, closest)) = closest else {
+        return;
+    };
+    *snap = closest;
+    cursor.position = closest.position;
+    if closest.target != Entity::PLACEHOLDER {
+        picking.hovered = closest.target;
+    }
+}
+
+// F7 steps through the Shift angle increments and F8 toggles ortho
+pub fn handle_angle_snap_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut angle_snap: ResMut<AngleSnap>,
+) {
+    if keyboard.just_pressed(KeyCode::F7) {
+        let next = ANGLE_INCREMENTS
+            .iter()
+            .position(|increment| *increment == angle_snap.increment)
+            .map_or(0, |i| (i + 1) % ANGLE_INCREMENTS.len());
+        angle_snap.increment = ANGLE_INCREMENTS[next];
+        info!("Angle snapping: {}°", angle_snap.increment);
+    }
+    if keyboard.just_pressed(KeyCode::F8) {
+        angle_snap.is_ortho = !angle_snap.is_ortho;
+        info!("Ortho: {}", angle_snap.is_ortho);
+    }
+}
+
+// The free end of the line keeps its distance along the nearest allowed direction from the
+// segment start. Snapping to geometry wins over both.
+pub fn snap_line_angle(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<Cursor>,
+    snap: Res<Snap>,
+    angle_snap: Res<AngleSnap>,
+    current: Res<Current>,
+    lines: Query<&Line>,
+    dots: Query<&Transform, With<Dot>>,
+) {
+    let increment = if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        angle_snap.increment
+    } else if angle_snap.is_ortho {
+        90.
+    } else {
+        return;
+    };
+    // Tangent arcs follow the cursor freely
+    if snap.kind.is_some() || !current.curves.is_empty() {
+        return;
+    }
+    let Some(start) = current
+        .lines
+        .first()
+        .and_then(|line| lines.get(*line).ok())
+        .and_then(|line| dots.get(line.start).ok())
+        .map(|transform| transform.translation)
+    else {
+        return;
+    };
+    let offset = (cursor.position - start).truncate();
+    if offset.length() < f32::EPSILON {
+        return;
+    }
+    let step = increment.to_radians();
+    let direction = Vec2::from_angle((offset.to_angle() / step).round() * step);
+    cursor.position = start + (direction * offset.dot(direction)).extend(0.);
+}
+
+// Each kind of snap has its own marker around the snapped point
+pub fn display_snap_marker(
+    mut gizmos: Gizmos,
+    snap: Res<Snap>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+) {
+    let Some(kind) = snap.kind else {
+        return;
+    };
+    let (camera, camera_transform) = *camera;
+    let (Ok(center), Ok(beside)) = (
+        camera.world_to_viewport(camera_transform, snap.position),
+        camera.world_to_viewport(camera_transform, snap.position + Vec3::X),
+    ) else {
+        return;
+    };
+    let size = SNAP_MARKER_PIXELS / center.distance(beside).max(f32::EPSILON);
+    let color = color_from_hex(GOLD_YELLOW);
+    let position = snap.position;
+    let corner = |x: f32, y: f32| position + Vec3::new(x, y, 0.) * size;
+    match kind {
+        SnapKind::Endpoint => {
+            gizmos.rect(
+                Isometry3d::from_translation(position),
+                Vec2::splat(size * 2.),
+                color,
+            );
+        }
+        SnapKind::Center => {
+            gizmos.circle(Isometry3d::from_translation(position), size, color);
+            gizmos.line(corner(-0.5, 0.), corner(0.5, 0.), color);
+            gizmos.line(corner(0., -0.5), corner(0., 0.5), color);
+        }
+        SnapKind::Midpoint => {
+            gizmos.linestrip(
+                [
+                    corner(-1., -0.8),
+                    corner(1., -0.8),
+                    corner(0., 1.),
+                    corner(-1., -0.8),
+                ],
+                color,
+            );
+        }
+        SnapKind::Intersection => {
+            gizmos.line(corner(-1., -1.), corner(1., 1.), color);
+            gizmos.line(corner(-1., 1.), corner(1., -1.), color);
+        }
+        SnapKind::Perpendicular => {
+            gizmos.linestrip([corner(-1., 1.), corner(-1., -1.), corner(1., -1.)], color);
+            gizmos.linestrip([corner(-1., 0.), corner(0., 0.), corner(0., -1.)], color);
+        }
+        SnapKind::Tangent => {
+            gizmos.circle(Isometry3d::from_translation(position), size * 0.7, color);
+            gizmos.line(corner(-1., 0.7), corner(1., 0.7), color);
+        }
+    }
+}
+
+// Foot of the perpendicular from a point onto a segment, if it lands inside the segment
+fn perpendicular_foot(from: Vec3, start: Vec3, end: Vec3) -> Option<Vec3> {
+    let direction = end - start;
+    let t = (from - start).dot(direction) / direction.length_squared();
+    let foot = start + direction * t;
+    ((0.0..=1.0).contains(&t) && foot.distance(from) > f32::EPSILON).then_some(foot)
+}
+
+// Crossing point of two segments
+fn intersection(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> Option<Vec3> {
+    let (first, second) = ((b - a).truncate(), (d - c).truncate());
+    let denominator = first.perp_dot(second);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let offset = (c - a).truncate();
+    let t = offset.perp_dot(second) / denominator;
+    let u = offset.perp_dot(first) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(a + (b - a) * t)
+}
+
+// Points where lines from a point outside a circle touch it
+fn tangent_points(from: Vec3, center: Vec3, radius: f32) -> Vec<Vec3> {
+    let offset = (from - center).truncate();
+    let distance = offset.length();
+    if distance <= radius {
+        return Vec::new();
+    }
+    let angle = (radius / distance).acos();
+    let direction = offset / distance;
+    [angle, -angle]
+        .map(|angle| center + (Vec2::from_angle(angle).rotate(direction) * radius).extend(0.))
+        .to_vec()
+}